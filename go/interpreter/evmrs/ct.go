@@ -38,7 +38,7 @@ type ctAdapter struct{}
 
 func (a ctAdapter) StepN(state *st.State, numSteps int) (*st.State, error) {
 	vmParams := utils.ToVmParameters(state)
-	if vmParams.Revision > newestSupportedRevision {
+	if !isSupportedRevision(vmParams.Revision) {
 		return state, &tosca.ErrUnsupportedRevision{Revision: vmParams.Revision}
 	}
 
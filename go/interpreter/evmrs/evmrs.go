@@ -41,10 +41,21 @@ type evmrsInstance struct {
 	e *evmc.EvmcInterpreter
 }
 
-const newestSupportedRevision = tosca.R13_Cancun
+// The revisions which can be run through this wrapper. The interpreter itself
+// also supports Frontier through Petersburg and Prague, but tosca.Revision has
+// no values for them, so they are only reachable through the Rust crate and
+// its evmc interface.
+const (
+	oldestSupportedRevision = tosca.R07_Istanbul
+	newestSupportedRevision = tosca.R13_Cancun
+)
+
+func isSupportedRevision(revision tosca.Revision) bool {
+	return revision >= oldestSupportedRevision && revision <= newestSupportedRevision
+}
 
 func (e *evmrsInstance) Run(params tosca.Parameters) (tosca.Result, error) {
-	if params.Revision > newestSupportedRevision {
+	if !isSupportedRevision(params.Revision) {
 		return tosca.Result{}, &tosca.ErrUnsupportedRevision{Revision: params.Revision}
 	}
 	return e.e.Run(params)
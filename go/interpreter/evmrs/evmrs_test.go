@@ -168,3 +168,13 @@ func TestEvmcSteppableInterpreter_BlobHashCanBeRead(t *testing.T) {
 		t.Errorf("unexpected output, wanted %v, got %v", blobhashes[0], resultState.ReturnData)
 	}
 }
+
+func TestEvmrs_RejectsRevisionsOutsideOfSupportedRange(t *testing.T) {
+	interpreter := tosca.GetInterpreter("evmrs")
+	for _, revision := range []tosca.Revision{oldestSupportedRevision - 1, newestSupportedRevision + 1} {
+		_, err := interpreter.Run(tosca.Parameters{Revision: revision})
+		if _, ok := err.(*tosca.ErrUnsupportedRevision); !ok {
+			t.Errorf("unexpected error for revision %v, got %v", revision, err)
+		}
+	}
+}
@@ -0,0 +1,86 @@
+use std::iter;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use evmc_vm::StatusCode;
+use evmrs::Evm;
+
+mod common;
+
+const OPS_PER_ITERATION: usize = 16;
+
+/// Pushes the operands and then executes the opcode `OPS_PER_ITERATION` times per loop iteration
+/// on copies of them. The first operand is the top of the stack.
+fn op_loop_code(op: u8, operands: &[[u8; 32]], iterations: u16) -> Vec<u8> {
+    let mut prologue = Vec::new();
+    for operand in operands.iter().rev() {
+        prologue.push(0x7f); // PUSH32
+        prologue.extend_from_slice(operand);
+    }
+    let mut body = Vec::new();
+    for _ in 0..OPS_PER_ITERATION {
+        // DUPn skips the counter on top of the stack.
+        body.extend(iter::repeat_n(0x80 + operands.len() as u8, operands.len()));
+        body.push(op);
+        body.push(0x50); // POP
+    }
+    common::loop_code(&prologue, &body, iterations)
+}
+
+fn small(value: u64) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    bytes[24..].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+/// Returns a value with `len` significant bytes.
+fn large(len: usize, seed: u8) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes[32 - len..].iter_mut().enumerate() {
+        *byte = seed.wrapping_mul(31).wrapping_add(i as u8 * 97) | 1;
+    }
+    bytes
+}
+
+fn arithmetic(c: &mut Criterion) {
+    let evm = Evm::new();
+    let message = common::message();
+
+    let binary = |a, b| vec![a, b];
+    let ternary = |a, b, m| vec![a, b, m];
+    #[rustfmt::skip]
+    let ops = [
+        ("add", 0x01, binary(small(0x1234_5678), small(0x9abc_def0)), binary(large(32, 1), large(32, 2))),
+        ("mul", 0x02, binary(small(0x1234_5678), small(0x9abc_def0)), binary(large(32, 1), large(32, 2))),
+        ("sub", 0x03, binary(small(0x9abc_def0), small(0x1234_5678)), binary(large(32, 1), large(32, 2))),
+        ("div", 0x04, binary(small(0x9abc_def0), small(0x1234)), binary(large(32, 1), large(20, 2))),
+        ("sdiv", 0x05, binary(small(0x9abc_def0), small(0x1234)), binary(large(32, 1), large(20, 2))),
+        ("mod", 0x06, binary(small(0x9abc_def0), small(0x1234)), binary(large(32, 1), large(20, 2))),
+        ("smod", 0x07, binary(small(0x9abc_def0), small(0x1234)), binary(large(32, 1), large(20, 2))),
+        ("addmod", 0x08, ternary(small(0x1234), small(0x5678), small(0x9abc)), ternary(large(32, 1), large(32, 2), large(20, 3))),
+        ("mulmod", 0x09, ternary(small(0x1234), small(0x5678), small(0x9abc)), ternary(large(32, 1), large(32, 2), large(20, 3))),
+        ("exp", 0x0a, binary(small(3), small(0x1234)), binary(large(32, 1), large(32, 2))),
+        ("signextend", 0x0b, binary(small(3), small(0x80)), binary(small(15), large(32, 1))),
+        ("lt", 0x10, binary(small(0x1234), small(0x5678)), binary(large(32, 1), large(32, 2))),
+        ("slt", 0x12, binary(small(0x1234), small(0x5678)), binary(large(32, 1), large(32, 2))),
+        ("byte", 0x1a, binary(small(31), small(0x1234)), binary(small(7), large(32, 1))),
+        ("shl", 0x1b, binary(small(3), small(0x1234)), binary(small(100), large(32, 1))),
+        ("shr", 0x1c, binary(small(3), small(0x1234)), binary(small(100), large(32, 1))),
+        ("sar", 0x1d, binary(small(3), small(0x1234)), binary(small(100), large(32, 1))),
+    ];
+
+    let mut group = c.benchmark_group("arithmetic");
+    for (name, op, small_operands, large_operands) in ops {
+        for (size, operands) in [("small", small_operands), ("large", large_operands)] {
+            let code = op_loop_code(op, &operands, 100);
+            let result = common::execute(&evm, &code, &message);
+            assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| common::execute(&evm, &code, &message))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, arithmetic);
+criterion_main!(benches);
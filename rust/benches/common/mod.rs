@@ -0,0 +1,68 @@
+// Every benchmark is a separate crate which only uses some of the helpers.
+#![allow(dead_code)]
+
+use evmc_vm::{
+    Address, ExecutionMessage, ExecutionResult, ExecutionTxContext, MessageKind, Revision, Uint256,
+};
+use evmrs::{Evm, InMemoryHost};
+
+pub fn message() -> ExecutionMessage {
+    ExecutionMessage::new(
+        MessageKind::EVMC_CALL,
+        0,
+        0,
+        i64::MAX,
+        Address::default(),
+        Address::default(),
+        None,
+        Uint256::default(),
+        Uint256::default(),
+        Address::default(),
+        None,
+    )
+}
+
+/// Returns an empty world state, in which all accounts and slots are cold.
+pub fn host() -> InMemoryHost {
+    InMemoryHost::new(Revision::EVMC_CANCUN, ExecutionTxContext::default())
+}
+
+/// Executes the code with a fresh [`host`], so that every execution starts with empty storage and
+/// cold accounts and slots.
+pub fn execute(evm: &Evm, code: &[u8], message: &ExecutionMessage) -> ExecutionResult {
+    execute_with_host(evm, code, message, &mut host())
+}
+
+pub fn execute_with_host(
+    evm: &Evm,
+    code: &[u8],
+    message: &ExecutionMessage,
+    host: &mut InMemoryHost,
+) -> ExecutionResult {
+    evm.execute(Revision::EVMC_CANCUN, code, message, host)
+        .unwrap()
+}
+
+/// Executes `prologue` and then `body` `iterations` times. The loop counter is on top of the stack
+/// when `body` starts and must be there again when it ends.
+pub fn loop_code(prologue: &[u8], body: &[u8], iterations: u16) -> Vec<u8> {
+    let mut code = prologue.to_vec();
+    code.push(0x61); // PUSH2 iterations
+    code.extend_from_slice(&iterations.to_be_bytes());
+    let loop_start = code.len() as u16;
+    code.push(0x5b); // JUMPDEST
+    code.extend_from_slice(body);
+    code.extend_from_slice(&[
+        0x60, 0x01, // PUSH1 1
+        0x90, // SWAP1
+        0x03, // SUB
+        0x80, // DUP1
+        0x61, // PUSH2 loop_start
+    ]);
+    code.extend_from_slice(&loop_start.to_be_bytes());
+    code.extend_from_slice(&[
+        0x57, // JUMPI
+        0x00, // STOP
+    ]);
+    code
+}
@@ -0,0 +1,102 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use evmc_vm::StatusCode;
+use evmrs::Evm;
+
+mod common;
+
+/// `keccak256("Transfer(address,address,uint256)")`
+const TRANSFER_TOPIC: [u8; 32] = [
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
+
+/// Mints `transfers` tokens to the caller and then transfers one token at a time to the addresses
+/// `transfers..1`, like an ERC-20 contract with `mapping(address => uint256) balances` at slot 0
+/// does: update both balances, reverting if the balance is too low, and emit a `Transfer` event.
+fn transfers_code(transfers: u16) -> Vec<u8> {
+    let [hi, lo] = transfers.to_be_bytes();
+    let prologue = [
+        0x60, 0x08, // PUSH1 mint
+        0x56, // JUMP
+        // revert (3):
+        0x5b, // JUMPDEST
+        0x60, 0x00, // PUSH1 0
+        0x80, // DUP1
+        0xfd, // REVERT
+        // mint (8):
+        0x5b, // JUMPDEST
+        0x61, hi, lo,   // PUSH2 transfers
+        0x33, // CALLER
+        0x60, 0x00, // PUSH1 0
+        0x52, // MSTORE
+        0x60, 0x40, // PUSH1 64
+        0x60, 0x00, // PUSH1 0
+        0x20, // SHA3
+        0x55, // SSTORE
+    ];
+    let mut body = vec![
+        // balance slot of the sender
+        0x33, // CALLER
+        0x60, 0x00, // PUSH1 0
+        0x52, // MSTORE
+        0x60, 0x40, // PUSH1 64
+        0x60, 0x00, // PUSH1 0
+        0x20, // SHA3
+        // subtract amount from the balance of the sender
+        0x80, // DUP1
+        0x54, // SLOAD
+        0x80, // DUP1
+        0x60, 0x01, // PUSH1 1
+        0x11, // GT
+        0x60, 0x03, // PUSH1 revert
+        0x57, // JUMPI
+        0x60, 0x01, // PUSH1 1
+        0x90, // SWAP1
+        0x03, // SUB
+        0x90, // SWAP1
+        0x55, // SSTORE
+        // balance slot of the recipient
+        0x80, // DUP1
+        0x60, 0x00, // PUSH1 0
+        0x52, // MSTORE
+        0x60, 0x40, // PUSH1 64
+        0x60, 0x00, // PUSH1 0
+        0x20, // SHA3
+        // add amount to the balance of the recipient
+        0x80, // DUP1
+        0x54, // SLOAD
+        0x60, 0x01, // PUSH1 1
+        0x01, // ADD
+        0x90, // SWAP1
+        0x55, // SSTORE
+        // Transfer(sender, recipient, amount)
+        0x60, 0x01, // PUSH1 1
+        0x60, 0x00, // PUSH1 0
+        0x52, // MSTORE
+        0x80, // DUP1
+        0x33, // CALLER
+        0x7f, // PUSH32 TRANSFER_TOPIC
+    ];
+    body.extend_from_slice(&TRANSFER_TOPIC);
+    body.extend_from_slice(&[
+        0x60, 0x20, // PUSH1 32
+        0x60, 0x00, // PUSH1 0
+        0xa3, // LOG3
+    ]);
+    common::loop_code(&prologue, &body, transfers)
+}
+
+fn erc20(c: &mut Criterion) {
+    let evm = Evm::new();
+    let message = common::message();
+
+    let code = transfers_code(1000);
+    let result = common::execute(&evm, &code, &message);
+    assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+    c.bench_function("erc20_transfers", |b| {
+        b.iter(|| common::execute(&evm, &code, &message))
+    });
+}
+
+criterion_group!(benches, erc20);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use evmc_vm::StatusCode;
+use evmrs::Evm;
+
+mod common;
+
+/// Computes `fib(n)` recursively with internal calls, which stresses jumps and stack operations,
+/// and returns it as a 32 byte word. `fib` expects the return address and `n` on the stack and
+/// leaves the result.
+fn fibonacci_code(n: u8) -> Vec<u8> {
+    vec![
+        0x60, 0x07, // PUSH1 ret
+        0x60, n, // PUSH1 n
+        0x60, 0x10, // PUSH1 fib
+        0x56, // JUMP
+        // ret (7):
+        0x5b, // JUMPDEST
+        0x60, 0x00, // PUSH1 0
+        0x52, // MSTORE
+        0x60, 0x20, // PUSH1 32
+        0x60, 0x00, // PUSH1 0
+        0xf3, // RETURN
+        // fib (16):
+        0x5b, // JUMPDEST
+        0x80, // DUP1
+        0x60, 0x02, // PUSH1 2
+        0x11, // GT
+        0x60, 0x33, // PUSH1 base
+        0x57, // JUMPI
+        0x60, 0x22, // PUSH1 r1
+        0x81, // DUP2
+        0x60, 0x01, // PUSH1 1
+        0x90, // SWAP1
+        0x03, // SUB
+        0x60, 0x10, // PUSH1 fib
+        0x56, // JUMP
+        // r1 (34):
+        0x5b, // JUMPDEST
+        0x60, 0x2d, // PUSH1 r2
+        0x82, // DUP3
+        0x60, 0x02, // PUSH1 2
+        0x90, // SWAP1
+        0x03, // SUB
+        0x60, 0x10, // PUSH1 fib
+        0x56, // JUMP
+        // r2 (45):
+        0x5b, // JUMPDEST
+        0x01, // ADD
+        0x90, // SWAP1
+        0x50, // POP
+        0x90, // SWAP1
+        0x56, // JUMP
+        // base (51):
+        0x5b, // JUMPDEST
+        0x90, // SWAP1
+        0x56, // JUMP
+    ]
+}
+
+fn fibonacci(n: u8) -> u64 {
+    let (mut a, mut b) = (0u64, 1);
+    for _ in 0..n {
+        (a, b) = (b, a + b);
+    }
+    a
+}
+
+fn fibonacci_bench(c: &mut Criterion) {
+    let evm = Evm::new();
+    let message = common::message();
+
+    let mut group = c.benchmark_group("fibonacci");
+    for n in [10, 15, 20] {
+        let code = fibonacci_code(n);
+        let result = common::execute(&evm, &code, &message);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        let output = result.output().unwrap();
+        assert_eq!(output[24..], fibonacci(n).to_be_bytes());
+
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            b.iter(|| common::execute(&evm, &code, &message))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fibonacci_bench);
+criterion_main!(benches);
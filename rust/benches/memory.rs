@@ -0,0 +1,72 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use evmc_vm::StatusCode;
+use evmrs::Evm;
+
+mod common;
+
+fn push3(value: u32) -> [u8; 4] {
+    let [_, a, b, c] = value.to_be_bytes();
+    [0x62, a, b, c] // PUSH3 value
+}
+
+/// Grows the memory word by word up to `size` bytes by storing to increasing offsets.
+fn memory_expansion_code(size: u32) -> Vec<u8> {
+    let mut code = vec![
+        0x60, 0x00, // PUSH1 0
+        0x5b, // JUMPDEST
+        0x80, // DUP1
+        0x80, // DUP1
+        0x52, // MSTORE
+        0x60, 0x20, // PUSH1 32
+        0x01, // ADD
+        0x80, // DUP1
+    ];
+    code.extend(push3(size));
+    code.extend([
+        0x11, // GT
+        0x60, 0x02, // PUSH1 2
+        0x57, // JUMPI
+        0x00, // STOP
+    ]);
+    code
+}
+
+/// Copies `size` bytes from the start of the memory directly behind them in a loop.
+fn mcopy_code(size: u32, iterations: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(push3(size)); // length
+    body.extend([0x60, 0x00]); // PUSH1 0 (source)
+    body.extend(push3(size)); // destination
+    body.push(0x5e); // MCOPY
+    common::loop_code(&[], &body, iterations)
+}
+
+fn memory(c: &mut Criterion) {
+    let evm = Evm::new();
+    let message = common::message();
+
+    let mut group = c.benchmark_group("memory_expansion");
+    for size in [1 << 10, 1 << 15, 1 << 20] {
+        let code = memory_expansion_code(size);
+        let result = common::execute(&evm, &code, &message);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| common::execute(&evm, &code, &message))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("mcopy");
+    for size in [32, 1 << 10, 1 << 15] {
+        let code = mcopy_code(size, 100);
+        let result = common::execute(&evm, &code, &message);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| common::execute(&evm, &code, &message))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, memory);
+criterion_main!(benches);
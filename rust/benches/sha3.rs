@@ -0,0 +1,38 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use evmrs::Evm;
+
+mod common;
+
+/// Computes the storage slot `keccak256(key . slot)` of a mapping in a loop, like Solidity does
+/// for every access to `mapping(uint256 => ...)` at slot 0. The keys are `counter & 0x0f`, so there
+/// are only 16 distinct preimages.
+fn mapping_slots_code(iterations: u16) -> Vec<u8> {
+    let body = [
+        0x80, // DUP1
+        0x60, 0x0f, // PUSH1 0x0f
+        0x16, // AND
+        0x60, 0x00, // PUSH1 0x00
+        0x52, // MSTORE
+        0x60, 0x40, // PUSH1 0x40
+        0x60, 0x00, // PUSH1 0x00
+        0x20, // SHA3
+        0x50, // POP
+    ];
+    common::loop_code(&[], &body, iterations)
+}
+
+fn sha3(c: &mut Criterion) {
+    let code = mapping_slots_code(1000);
+    let message = common::message();
+
+    for (name, sha3_cache) in [("cached", true), ("uncached", false)] {
+        let mut evm = Evm::new();
+        evm.set_sha3_cache(sha3_cache);
+        c.bench_function(&format!("sha3_mapping_slots_{name}"), |b| {
+            b.iter(|| common::execute(&evm, &code, &message))
+        });
+    }
+}
+
+criterion_group!(benches, sha3);
+criterion_main!(benches);
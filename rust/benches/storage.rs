@@ -0,0 +1,64 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use evmc_vm::StatusCode;
+use evmrs::Evm;
+
+mod common;
+
+fn storage(c: &mut Criterion) {
+    let evm = Evm::new();
+    let message = common::message();
+
+    let patterns: [(&str, &[u8]); 4] = [
+        (
+            // Only the first access is cold.
+            "sload_same_slot",
+            &[
+                0x60, 0x00, // PUSH1 0
+                0x54, // SLOAD
+                0x50, // POP
+            ],
+        ),
+        (
+            // Every access is cold.
+            "sload_distinct_slots",
+            &[
+                0x80, // DUP1
+                0x54, // SLOAD
+                0x50, // POP
+            ],
+        ),
+        (
+            // Every store adds a new slot.
+            "sstore_distinct_slots",
+            &[
+                0x80, // DUP1
+                0x80, // DUP1
+                0x55, // SSTORE
+            ],
+        ),
+        (
+            // Increments a counter, which is read and modified over and over again.
+            "sload_sstore_counter",
+            &[
+                0x60, 0x00, // PUSH1 0
+                0x54, // SLOAD
+                0x60, 0x01, // PUSH1 1
+                0x01, // ADD
+                0x60, 0x00, // PUSH1 0
+                0x55, // SSTORE
+            ],
+        ),
+    ];
+
+    let mut group = c.benchmark_group("storage");
+    for (name, body) in patterns {
+        let code = common::loop_code(&[], body, 1000);
+        let result = common::execute(&evm, &code, &message);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        group.bench_function(name, |b| b.iter(|| common::execute(&evm, &code, &message)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, storage);
+criterion_main!(benches);
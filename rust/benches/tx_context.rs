@@ -0,0 +1,45 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use evmc_vm::StatusCode;
+use evmrs::Evm;
+
+mod common;
+
+/// Reads every field of the transaction context which is exposed as an opcode in a loop.
+fn tx_context_code(iterations: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    for op in [
+        0x32, // ORIGIN
+        0x3a, // GASPRICE
+        0x41, // COINBASE
+        0x42, // TIMESTAMP
+        0x43, // NUMBER
+        0x44, // PREVRANDAO
+        0x45, // GASLIMIT
+        0x46, // CHAINID
+        0x48, // BASEFEE
+        0x4a, // BLOBBASEFEE
+    ] {
+        body.extend([op, 0x50]); // op POP
+    }
+    common::loop_code(&[], &body, iterations)
+}
+
+fn tx_context(c: &mut Criterion) {
+    let iterations = 1000;
+    let code = tx_context_code(iterations);
+    let message = common::message();
+    let evm = Evm::new();
+    let mut host = common::host();
+    assert_eq!(
+        common::execute_with_host(&evm, &code, &message, &mut host).status_code(),
+        StatusCode::EVMC_SUCCESS
+    );
+    assert_eq!(host.tx_context_reads(), 1);
+
+    c.bench_function("tx_context_opcodes", |b| {
+        b.iter(|| common::execute(&evm, &code, &message))
+    });
+}
+
+criterion_group!(benches, tx_context);
+criterion_main!(benches);
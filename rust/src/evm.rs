@@ -0,0 +1,258 @@
+use std::{borrow::Cow, sync::Arc};
+
+use evmc_vm::{ExecutionMessage, ExecutionResult, Revision, StepResult, StepStatusCode};
+
+use crate::{
+    host::Host,
+    interpreter::{
+        self, AnalysisCache, CodeAnalysis, CodeState, ExecutionError, Memory, RunResult, Sha3Cache,
+        Stack,
+    },
+    types::u256,
+};
+
+/// The interpreter together with its caches, which are shared by all executions. Executions only
+/// need a shared reference, so one instance can be used from multiple threads.
+pub struct Evm {
+    analysis_cache_enabled: bool,
+    analysis_cache: AnalysisCache,
+    super_instructions_enabled: bool,
+    sha3_cache_enabled: bool,
+    sha3_cache: Sha3Cache,
+    preserve_failure_state: bool,
+}
+
+impl Default for Evm {
+    fn default() -> Self {
+        Self {
+            analysis_cache_enabled: true,
+            analysis_cache: AnalysisCache::default(),
+            super_instructions_enabled: false,
+            sha3_cache_enabled: false,
+            sha3_cache: Sha3Cache::default(),
+            preserve_failure_state: false,
+        }
+    }
+}
+
+impl Evm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the analysis of code is cached by its hash. Enabled by default.
+    pub fn set_analysis_cache(&mut self, enabled: bool) {
+        self.analysis_cache_enabled = enabled;
+    }
+
+    /// Whether common opcode sequences are executed as one instruction. Disabled by default.
+    pub fn set_super_instructions(&mut self, enabled: bool) {
+        self.super_instructions_enabled = enabled;
+    }
+
+    /// Whether the results of SHA3 on short inputs are cached. Disabled by default, because it only
+    /// pays off if the same inputs are hashed over and over again.
+    pub fn set_sha3_cache(&mut self, enabled: bool) {
+        self.sha3_cache_enabled = enabled;
+    }
+
+    /// Whether [`Evm::step_n`] reports a failure as a [`StepResult`] with status
+    /// `EVMC_STEP_FAILED` which contains the pc, gas, gas refund, stack, memory and return data
+    /// right before the failing opcode, instead of as an error. Disabled by default.
+    pub fn set_preserve_failure_state(&mut self, enabled: bool) {
+        self.preserve_failure_state = enabled;
+    }
+
+    /// Executes `code` in the context of `message` until it halts. Reverts are returned as result,
+    /// only exceptional halts are returned as error.
+    pub fn execute(
+        &self,
+        revision: Revision,
+        code: &[u8],
+        message: &ExecutionMessage,
+        host: &mut dyn Host,
+    ) -> Result<ExecutionResult, ExecutionError> {
+        let code_analysis = self.code_analysis(code, message, revision);
+        self.run(
+            revision,
+            code,
+            &code_analysis,
+            message,
+            host,
+            StepStatusCode::EVMC_STEP_RUNNING,
+            0,
+            0,
+            Stack::pooled(),
+            Memory::pooled(),
+            None,
+            None,
+        )
+        .map(Into::into)
+    }
+
+    /// Executes at most `steps` opcodes of `code`, starting at `pc` with the given machine state,
+    /// and returns the state afterwards. The gas left is taken from `message`. Stack and memory are
+    /// modified in place and moved into the [`StepResult`], so they are not copied.
+    #[allow(clippy::too_many_arguments)]
+    pub fn step_n(
+        &self,
+        revision: Revision,
+        code: &[u8],
+        message: &ExecutionMessage,
+        host: &mut dyn Host,
+        step_status: StepStatusCode,
+        pc: u64,
+        gas_refund: i64,
+        stack: Vec<u256>,
+        memory: Vec<u8>,
+        last_call_return_data: &[u8],
+        steps: i32,
+    ) -> Result<StepResult, ExecutionError> {
+        let code_analysis = self.code_analysis(code, message, revision);
+        // Every step pushes at most one element, so the stack is grown once up front and never
+        // reallocated while stepping. The last call return data is only copied when it is returned
+        // unchanged.
+        self.run(
+            revision,
+            code,
+            &code_analysis,
+            message,
+            host,
+            step_status,
+            pc,
+            gas_refund,
+            Stack::with_growth(stack, steps.max(0) as usize),
+            Memory::new(memory),
+            Some(Cow::Borrowed(last_call_return_data)),
+            Some(steps),
+        )
+        .map(Into::into)
+    }
+
+    fn code_analysis(
+        &self,
+        code: &[u8],
+        message: &ExecutionMessage,
+        revision: Revision,
+    ) -> Arc<CodeAnalysis> {
+        if self.analysis_cache_enabled {
+            self.analysis_cache.get_or_analyze(
+                message.code_hash().map(|hash| (*hash).into()),
+                code,
+                revision,
+            )
+        } else {
+            Arc::new(CodeAnalysis::for_revision(code, revision))
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run<'a>(
+        &self,
+        revision: Revision,
+        code: &'a [u8],
+        code_analysis: &'a CodeAnalysis,
+        message: &ExecutionMessage,
+        host: &mut dyn Host,
+        step_status_code: StepStatusCode,
+        pc: u64,
+        gas_refund: i64,
+        stack: Stack,
+        memory: Memory,
+        last_call_return_data: Option<Cow<'a, [u8]>>,
+        steps: Option<i32>,
+    ) -> Result<RunResult<'a>, ExecutionError> {
+        interpreter::run(
+            revision,
+            message,
+            host,
+            step_status_code,
+            CodeState::new(code, code_analysis, pc as usize),
+            gas_refund,
+            stack,
+            memory,
+            last_call_return_data,
+            steps,
+            self.super_instructions_enabled,
+            self.preserve_failure_state,
+            self.sha3_cache_enabled.then_some(&self.sha3_cache),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evmc_vm::{
+        Address, ExecutionMessage, MessageKind, Revision, StatusCode, StepStatusCode, Uint256,
+    };
+
+    use crate::{
+        evm::Evm,
+        interpreter::FailureReason,
+        types::{u256, Opcode},
+        InMemoryHost,
+    };
+
+    #[test]
+    fn step_n_preserves_state_on_failure() {
+        let code = [
+            0x60, 0x01, // PUSH1 1
+            0x67, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // PUSH8 0xffffffffffffffff
+            0x52, // MSTORE
+        ];
+        let message = ExecutionMessage::new(
+            MessageKind::EVMC_CALL,
+            0,
+            0,
+            100_000,
+            Address::default(),
+            Address::default(),
+            None,
+            Uint256::default(),
+            Uint256::default(),
+            Address::default(),
+            None,
+        );
+        let step_n = |evm: &Evm| {
+            let mut host = InMemoryHost::new(Revision::EVMC_CANCUN, Default::default());
+            evm.step_n(
+                Revision::EVMC_CANCUN,
+                &code,
+                &message,
+                &mut host,
+                StepStatusCode::EVMC_STEP_RUNNING,
+                0,
+                0,
+                Vec::new(),
+                vec![0; 32],
+                &[1, 2],
+                3,
+            )
+        };
+
+        let mut evm = Evm::new();
+        let err = step_n(&evm).unwrap_err();
+        assert_eq!(err.opcode, Some(Opcode::MStore));
+        assert_eq!(err.pc, 11);
+        assert_eq!(
+            err.reason,
+            FailureReason::MemoryOverflow {
+                offset: u64::MAX.into(),
+                len: 32u8.into(),
+            }
+        );
+
+        evm.set_preserve_failure_state(true);
+        let result = step_n(&evm).unwrap();
+        assert_eq!(result.step_status_code(), StepStatusCode::EVMC_STEP_FAILED);
+        assert_eq!(result.status_code(), StatusCode::EVMC_OUT_OF_GAS);
+        assert_eq!(result.pc(), 11);
+        assert_eq!(result.gas_left(), 100_000 - 3 - 3);
+        assert_eq!(
+            result.stack(),
+            &[u256::from(1u8).into(), u256::from(u64::MAX).into()]
+        );
+        assert_eq!(result.memory(), &[0; 32]);
+        assert_eq!(result.last_call_return_data(), Some(&vec![1, 2]));
+    }
+}
@@ -2,22 +2,18 @@ use std::{mem, process};
 
 use evmc_vm::{
     ffi::evmc_capabilities, EvmcVm, ExecutionContext, ExecutionMessage, ExecutionResult, Revision,
-    StepResult, StepStatusCode, SteppableEvmcVm, Uint256,
+    SetOptionError, StepResult, StepStatusCode, SteppableEvmcVm, Uint256,
 };
 
-use crate::{
-    ffi::EVMC_CAPABILITY,
-    interpreter,
-    interpreter::{CodeState, Memory, Stack},
-    types::u256,
-};
+use crate::{evm::Evm, ffi::EVMC_CAPABILITY, host::Host, types::u256};
 
+/// The EVMC interface of [`Evm`].
 //#[evmc_declare::evmc_declare_vm("evmrs", "ewasm, evm", "0.1.0")]
-pub struct EvmRs;
+pub struct EvmRs(Evm);
 
 impl EvmcVm for EvmRs {
     fn init() -> Self {
-        EvmRs {}
+        EvmRs(Evm::new())
     }
 
     fn execute<'a>(
@@ -27,23 +23,25 @@ impl EvmcVm for EvmRs {
         message: &'a ExecutionMessage,
         context: Option<&'a mut ExecutionContext<'a>>,
     ) -> ExecutionResult {
-        run(
-            revision,
-            code,
-            message,
-            context,
-            StepStatusCode::EVMC_STEP_RUNNING,
-            0,
-            0,
-            Vec::with_capacity(1024),
-            Vec::new(),
-            None,
-            None,
-        )
-        .into()
+        self.0
+            .execute(revision, code, message, host(context))
+            .unwrap_or_else(|err| ExecutionResult::new(err.status_code(), 0, 0, None))
     }
 
-    fn set_option(&mut self, _: &str, _: &str) -> Result<(), evmc_vm::SetOptionError> {
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), SetOptionError> {
+        let set_option = match key {
+            "analysis_cache" => Evm::set_analysis_cache,
+            "super_instructions" => Evm::set_super_instructions,
+            "sha3_cache" => Evm::set_sha3_cache,
+            "preserve_failure_state" => Evm::set_preserve_failure_state,
+            _ => return Err(SetOptionError::InvalidKey),
+        };
+        let enabled = match value {
+            "true" => true,
+            "false" => false,
+            _ => return Err(SetOptionError::InvalidValue),
+        };
+        set_option(&mut self.0, enabled);
         Ok(())
     }
 }
@@ -63,36 +61,46 @@ impl SteppableEvmcVm for EvmRs {
         last_call_result_data: &'a mut [u8],
         steps: i32,
     ) -> StepResult {
-        run(
-            revision,
-            code,
-            message,
-            context,
-            step_status,
-            pc,
-            gas_refund,
-            stack.to_owned(),
-            memory.to_owned(),
-            Some(last_call_result_data.to_owned()),
-            Some(steps),
-        )
+        let stack = unsafe {
+            // SAFETY
+            // u256 is a newtype of Uint256 with repr(transparent) which guarantees the same memory
+            // layout.
+            mem::transmute::<&[Uint256], &[u256]>(stack)
+        };
+        // The buffers are owned by the client, but StepResult has to own its stack and memory, so
+        // they are copied once here. From then on they are modified in place.
+        self.0
+            .step_n(
+                revision,
+                code,
+                message,
+                host(context),
+                step_status,
+                pc,
+                gas_refund,
+                stack.to_vec(),
+                memory.to_vec(),
+                last_call_result_data,
+                steps,
+            )
+            .unwrap_or_else(|err| {
+                StepResult::new(
+                    StepStatusCode::EVMC_STEP_FAILED,
+                    err.status_code(),
+                    revision,
+                    0,
+                    0,
+                    0,
+                    None,
+                    Vec::new(),
+                    Vec::new(),
+                    None,
+                )
+            })
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn run<'a>(
-    revision: Revision,
-    code: &'a [u8],
-    message: &'a ExecutionMessage,
-    context: Option<&'a mut ExecutionContext<'a>>,
-    step_status_code: StepStatusCode,
-    pc: u64,
-    gas_refund: i64,
-    stack: Vec<Uint256>,
-    memory: Vec<u8>,
-    last_call_return_data: Option<Vec<u8>>,
-    steps: Option<i32>,
-) -> StepResult {
+fn host<'a>(context: Option<&'a mut ExecutionContext<'_>>) -> &'a mut dyn Host {
     assert_ne!(
         EVMC_CAPABILITY,
         evmc_capabilities::EVMC_CAPABILITY_PRECOMPILES
@@ -102,34 +110,5 @@ pub fn run<'a>(
         // If this is not the case it violates the EVMC spec and is an irrecoverable error.
         process::abort();
     };
-    interpreter::run(
-        revision,
-        message,
-        context,
-        step_status_code,
-        CodeState::new(code, pc as usize),
-        gas_refund,
-        // SAFETY
-        // u256 is a newtype of Uint256 with repr(transparent) which guarantees the same memory
-        // layout.
-        Stack::new(unsafe { mem::transmute::<Vec<Uint256>, Vec<u256>>(stack.to_owned()) }),
-        Memory::new(memory),
-        last_call_return_data,
-        steps,
-    )
-    .map(Into::into)
-    .unwrap_or_else(|(step_status_code, status_code)| {
-        StepResult::new(
-            step_status_code,
-            status_code,
-            revision,
-            0,
-            0,
-            0,
-            None,
-            Vec::new(),
-            Vec::new(),
-            None,
-        )
-    })
+    context
 }
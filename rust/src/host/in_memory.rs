@@ -0,0 +1,954 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    mem,
+};
+
+use evmc_vm::{
+    AccessStatus, Address, Bytes32, ExecutionMessage, ExecutionResult, ExecutionTxContext,
+    MessageKind, Revision, StatusCode, StepStatusCode, StorageStatus, Uint256,
+};
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    host::{delegate, Host},
+    interpreter::{self, CodeAnalysis, CodeState, Memory, Stack},
+    types::u256,
+};
+
+const MAX_CODE_SIZE: usize = 24576;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Account {
+    pub balance: Uint256,
+    pub nonce: u64,
+    pub code: Vec<u8>,
+    pub storage: HashMap<Bytes32, Bytes32>,
+}
+
+impl Account {
+    /// Whether the account is empty as defined by EIP-161.
+    pub fn is_empty(&self) -> bool {
+        self.balance == Uint256::default() && self.nonce == 0 && self.code.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub data: Vec<u8>,
+    pub topics: Vec<Bytes32>,
+}
+
+/// A change of the world state which can be undone.
+#[derive(Debug)]
+enum JournalEntry {
+    AccountCreated(Address),
+    Balance(Address, Uint256),
+    Nonce(Address, u64),
+    Code(Address, Vec<u8>),
+    Storage(Address, Bytes32, Bytes32),
+    TransientStorage(Address, Bytes32, Bytes32),
+    AccountAccessed(Address),
+    SlotAccessed(Address, Bytes32),
+    Log,
+    Created(Address),
+    Destructed(Address),
+}
+
+/// A [`Host`] which keeps the whole world state in memory. Nested calls and creates are executed
+/// by the interpreter. All changes are journaled, so that a failed call can be undone, and the
+/// state which only lives for one transaction is kept until [`InMemoryHost::finalize_transaction`]
+/// is called. Precompiled contracts are not supported.
+#[derive(Debug)]
+pub struct InMemoryHost {
+    revision: Revision,
+    tx_context: ExecutionTxContext,
+    /// The number of calls of [`Host::get_tx_context`], which is a round trip into the client for
+    /// other hosts.
+    tx_context_reads: Cell<usize>,
+    accounts: HashMap<Address, Account>,
+    /// The values of the storage slots at the start of the transaction, recorded on the first
+    /// write.
+    original_storage: HashMap<(Address, Bytes32), Bytes32>,
+    transient_storage: HashMap<(Address, Bytes32), Bytes32>,
+    accessed_accounts: HashSet<Address>,
+    accessed_slots: HashSet<(Address, Bytes32)>,
+    logs: Vec<Log>,
+    /// Accounts created in the current transaction.
+    created: HashSet<Address>,
+    /// Accounts which executed SELFDESTRUCT in the current transaction.
+    destructed: HashSet<Address>,
+    journal: Vec<JournalEntry>,
+}
+
+impl InMemoryHost {
+    pub fn new(revision: Revision, tx_context: ExecutionTxContext) -> Self {
+        Self {
+            revision,
+            tx_context,
+            tx_context_reads: Cell::new(0),
+            accounts: HashMap::new(),
+            original_storage: HashMap::new(),
+            transient_storage: HashMap::new(),
+            accessed_accounts: HashSet::new(),
+            accessed_slots: HashSet::new(),
+            logs: Vec::new(),
+            created: HashSet::new(),
+            destructed: HashSet::new(),
+            journal: Vec::new(),
+        }
+    }
+
+    pub fn account(&self, addr: &Address) -> Option<&Account> {
+        self.accounts.get(addr)
+    }
+
+    /// Sets the account without journaling, which is meant for setting up the state before a
+    /// transaction.
+    pub fn set_account(&mut self, addr: Address, account: Account) {
+        self.accounts.insert(addr, account);
+    }
+
+    /// Marks the accounts and storage slots as accessed which are warm from the start of a
+    /// transaction to `to`: the origin, the recipient, the coinbase since Shanghai (EIP-3651) and
+    /// the entries of the access list (EIP-2929, EIP-2930). For creates `to` is the address of the
+    /// new contract. Precompiled contracts are not supported and thus not included.
+    pub fn begin_transaction(&mut self, to: &Address, access_list: &[(Address, Vec<Bytes32>)]) {
+        self.accessed_accounts.insert(self.tx_context.tx_origin);
+        self.accessed_accounts.insert(*to);
+        if self.revision >= Revision::EVMC_SHANGHAI {
+            self.accessed_accounts
+                .insert(self.tx_context.block_coinbase);
+        }
+        for (addr, keys) in access_list {
+            self.accessed_accounts.insert(*addr);
+            self.accessed_slots
+                .extend(keys.iter().map(|key| (*addr, *key)));
+        }
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Returns how often the transaction context was read.
+    pub fn tx_context_reads(&self) -> usize {
+        self.tx_context_reads.get()
+    }
+
+    /// Returns a handle to the current state, which can be restored with
+    /// [`InMemoryHost::revert_to_snapshot`].
+    pub fn snapshot(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Undoes all changes since the snapshot was taken.
+    pub fn revert_to_snapshot(&mut self, snapshot: usize) {
+        while self.journal.len() > snapshot {
+            match self.journal.pop().unwrap() {
+                JournalEntry::AccountCreated(addr) => {
+                    self.accounts.remove(&addr);
+                }
+                JournalEntry::Balance(addr, balance) => self.journaled(addr).balance = balance,
+                JournalEntry::Nonce(addr, nonce) => self.journaled(addr).nonce = nonce,
+                JournalEntry::Code(addr, code) => self.journaled(addr).code = code,
+                JournalEntry::Storage(addr, key, value) => {
+                    set_or_remove(&mut self.journaled(addr).storage, key, value);
+                }
+                JournalEntry::TransientStorage(addr, key, value) => {
+                    set_or_remove(&mut self.transient_storage, (addr, key), value);
+                }
+                JournalEntry::AccountAccessed(addr) => {
+                    self.accessed_accounts.remove(&addr);
+                }
+                JournalEntry::SlotAccessed(addr, key) => {
+                    self.accessed_slots.remove(&(addr, key));
+                }
+                JournalEntry::Log => {
+                    self.logs.pop();
+                }
+                JournalEntry::Created(addr) => {
+                    self.created.remove(&addr);
+                }
+                JournalEntry::Destructed(addr) => {
+                    self.destructed.remove(&addr);
+                }
+            }
+        }
+    }
+
+    /// Deletes the self destructed accounts, clears all state which only lives for one transaction
+    /// and returns the emitted logs.
+    pub fn finalize_transaction(&mut self) -> Vec<Log> {
+        for addr in mem::take(&mut self.destructed) {
+            // Since Cancun only accounts created in the same transaction are deleted (EIP-6780).
+            if self.revision < Revision::EVMC_CANCUN || self.created.contains(&addr) {
+                self.accounts.remove(&addr);
+            }
+        }
+        self.original_storage.clear();
+        self.transient_storage.clear();
+        self.accessed_accounts.clear();
+        self.accessed_slots.clear();
+        self.created.clear();
+        self.journal.clear();
+        mem::take(&mut self.logs)
+    }
+
+    /// Returns the account and creates it if it does not exist yet.
+    fn account_mut(&mut self, addr: Address) -> &mut Account {
+        self.accounts.entry(addr).or_insert_with(|| {
+            self.journal.push(JournalEntry::AccountCreated(addr));
+            Account::default()
+        })
+    }
+
+    /// Returns an account which is referenced by the journal and therefore exists.
+    fn journaled(&mut self, addr: Address) -> &mut Account {
+        self.accounts.get_mut(&addr).unwrap()
+    }
+
+    fn set_balance(&mut self, addr: Address, balance: u256) {
+        let account = self.account_mut(addr);
+        let previous = mem::replace(&mut account.balance, balance.into());
+        self.journal.push(JournalEntry::Balance(addr, previous));
+    }
+
+    fn set_nonce(&mut self, addr: Address, nonce: u64) {
+        let account = self.account_mut(addr);
+        let previous = mem::replace(&mut account.nonce, nonce);
+        self.journal.push(JournalEntry::Nonce(addr, previous));
+    }
+
+    fn set_code(&mut self, addr: Address, code: Vec<u8>) {
+        let account = self.account_mut(addr);
+        let previous = mem::replace(&mut account.code, code);
+        self.journal.push(JournalEntry::Code(addr, previous));
+    }
+
+    fn transfer(&mut self, from: Address, to: Address, value: u256) -> bool {
+        let from_balance = u256::from(self.get_balance(&from));
+        if from_balance < value {
+            return false;
+        }
+        if value != u256::ZERO {
+            self.set_balance(from, from_balance - value);
+            let to_balance = u256::from(self.get_balance(&to));
+            self.set_balance(to, to_balance + value);
+        }
+        true
+    }
+
+    fn execute(&mut self, message: &ExecutionMessage, code: &[u8]) -> ExecutionResult {
+        let code_analysis = CodeAnalysis::for_revision(code, self.revision);
+        interpreter::run(
+            self.revision,
+            message,
+            self,
+            StepStatusCode::EVMC_STEP_RUNNING,
+            CodeState::new(code, &code_analysis, 0),
+            0,
+            Stack::pooled(),
+            Memory::pooled(),
+            None,
+            None,
+            false,
+            false,
+            None,
+        )
+        .map(Into::into)
+        .unwrap_or_else(|err| ExecutionResult::new(err.status_code(), 0, 0, None))
+    }
+
+    fn call_contract(&mut self, message: &ExecutionMessage) -> ExecutionResult {
+        let value = u256::from(*message.value());
+        if message.kind() == MessageKind::EVMC_CALL
+            && !self.transfer(*message.sender(), *message.recipient(), value)
+        {
+            return ExecutionResult::new(StatusCode::EVMC_INSUFFICIENT_BALANCE, 0, 0, None);
+        }
+        // The code address differs from the recipient for CALLCODE, DELEGATECALL and calls to
+        // accounts with a delegation designator (EIP-7702).
+        let code = self
+            .accounts
+            .get(message.code_address())
+            .map(|account| account.code.clone())
+            .unwrap_or_default();
+        if code.is_empty() {
+            return ExecutionResult::success(message.gas(), 0, None);
+        }
+        self.execute(message, &code)
+    }
+
+    fn create(&mut self, message: &ExecutionMessage) -> ExecutionResult {
+        let sender = *message.sender();
+        let eof = message.kind() == MessageKind::EVMC_EOFCREATE;
+        // EOFCREATE passes the init container as code and the input as call data.
+        let (init_code, input) = if eof {
+            (
+                message.code().map(Vec::as_slice).unwrap_or_default(),
+                message.input().map(Vec::as_slice),
+            )
+        } else {
+            (message.input().map(Vec::as_slice).unwrap_or_default(), None)
+        };
+        let nonce = self
+            .account(&sender)
+            .map(|account| account.nonce)
+            .unwrap_or(0);
+        let addr = if message.kind() == MessageKind::EVMC_CREATE {
+            create_address(&sender, nonce)
+        } else {
+            create2_address(&sender, message.create2_salt(), init_code)
+        };
+        // The nonce increment and the access of the address are not reverted if the creation
+        // fails.
+        self.set_nonce(sender, nonce + 1);
+        self.access_account(&addr);
+        // From Osaka, legacy creates can not execute EOF init code (EIP-7620).
+        if !eof && self.revision >= Revision::EVMC_OSAKA && init_code.starts_with(&[0xef, 0x00]) {
+            return ExecutionResult::failure();
+        }
+
+        let snapshot = self.snapshot();
+        if self
+            .account(&addr)
+            .is_some_and(|account| account.nonce != 0 || !account.code.is_empty())
+        {
+            return ExecutionResult::failure();
+        }
+        self.journal.push(JournalEntry::Created(addr));
+        self.created.insert(addr);
+        if self.revision >= Revision::EVMC_SPURIOUS_DRAGON {
+            self.set_nonce(addr, 1);
+        }
+        if !self.transfer(sender, addr, (*message.value()).into()) {
+            self.revert_to_snapshot(snapshot);
+            return ExecutionResult::new(StatusCode::EVMC_INSUFFICIENT_BALANCE, 0, 0, None);
+        }
+
+        let init_message = ExecutionMessage::new(
+            message.kind(),
+            message.flags(),
+            message.depth(),
+            message.gas(),
+            addr,
+            sender,
+            input,
+            *message.value(),
+            *message.create2_salt(),
+            addr,
+            None,
+        );
+        let result = self.execute(&init_message, init_code);
+        if result.status_code() != StatusCode::EVMC_SUCCESS {
+            self.revert_to_snapshot(snapshot);
+            return result;
+        }
+
+        let code = result.output().cloned().unwrap_or_default();
+        let mut gas_left = result.gas_left();
+        let deposit_cost = 200 * code.len() as i64;
+        let code_valid = (self.revision < Revision::EVMC_SPURIOUS_DRAGON
+            || code.len() <= MAX_CODE_SIZE)
+            && (eof || self.revision < Revision::EVMC_LONDON || code.first() != Some(&0xef));
+        if !code_valid || (gas_left < deposit_cost && self.revision >= Revision::EVMC_HOMESTEAD) {
+            self.revert_to_snapshot(snapshot);
+            return ExecutionResult::failure();
+        }
+        if gas_left >= deposit_cost {
+            gas_left -= deposit_cost;
+            self.set_code(addr, code);
+        }
+
+        let mut result = ExecutionResult::new(
+            StatusCode::EVMC_SUCCESS,
+            gas_left,
+            result.gas_refund(),
+            None,
+        );
+        result.set_create_address(addr);
+        result
+    }
+}
+
+impl Host for InMemoryHost {
+    fn account_exists(&self, addr: &Address) -> bool {
+        match self.accounts.get(addr) {
+            Some(account) => self.revision < Revision::EVMC_SPURIOUS_DRAGON || !account.is_empty(),
+            None => false,
+        }
+    }
+
+    fn get_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32 {
+        self.accounts
+            .get(addr)
+            .and_then(|account| account.storage.get(key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn set_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32) -> StorageStatus {
+        let current = self.get_storage(addr, key);
+        let original = *self
+            .original_storage
+            .entry((*addr, *key))
+            .or_insert(current);
+        set_or_remove(&mut self.account_mut(*addr).storage, *key, *value);
+        self.journal
+            .push(JournalEntry::Storage(*addr, *key, current));
+        storage_status(original, current, *value)
+    }
+
+    fn get_balance(&self, addr: &Address) -> Uint256 {
+        self.accounts
+            .get(addr)
+            .map(|account| account.balance)
+            .unwrap_or_default()
+    }
+
+    fn get_code_size(&self, addr: &Address) -> usize {
+        self.accounts
+            .get(addr)
+            .map(|account| account.code.len())
+            .unwrap_or_default()
+    }
+
+    fn get_code_hash(&self, addr: &Address) -> Bytes32 {
+        match self.accounts.get(addr) {
+            Some(account) if !account.is_empty() => keccak256(&account.code),
+            _ => Bytes32::default(),
+        }
+    }
+
+    fn copy_code(&self, addr: &Address, offset: usize, buffer: &mut [u8]) -> usize {
+        let code = self
+            .accounts
+            .get(addr)
+            .map(|account| account.code.as_slice())
+            .unwrap_or_default();
+        let code = code.get(offset..).unwrap_or_default();
+        let len = code.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&code[..len]);
+        len
+    }
+
+    fn selfdestruct(&mut self, addr: &Address, beneficiary: &Address) -> bool {
+        let balance = u256::from(self.get_balance(addr));
+        let deleted = self.revision < Revision::EVMC_CANCUN || self.created.contains(addr);
+        if addr != beneficiary {
+            self.transfer(*addr, *beneficiary, balance);
+        } else if deleted {
+            // The balance is burned.
+            self.set_balance(*addr, u256::ZERO);
+        }
+        let first = self.destructed.insert(*addr);
+        if first {
+            self.journal.push(JournalEntry::Destructed(*addr));
+        }
+        first
+    }
+
+    fn call(&mut self, message: &ExecutionMessage) -> ExecutionResult {
+        match message.kind() {
+            // Creates only revert part of their changes on failure.
+            MessageKind::EVMC_CREATE | MessageKind::EVMC_CREATE2 | MessageKind::EVMC_EOFCREATE => {
+                self.create(message)
+            }
+            _ => {
+                let snapshot = self.snapshot();
+                let result = self.call_contract(message);
+                if result.status_code() != StatusCode::EVMC_SUCCESS {
+                    self.revert_to_snapshot(snapshot);
+                }
+                result
+            }
+        }
+    }
+
+    fn get_tx_context(&self) -> ExecutionTxContext {
+        self.tx_context_reads.set(self.tx_context_reads.get() + 1);
+        self.tx_context.clone()
+    }
+
+    /// Block hashes are not tracked, so this always returns zero.
+    fn get_block_hash(&self, _number: i64) -> Bytes32 {
+        Bytes32::default()
+    }
+
+    fn emit_log(&mut self, addr: &Address, data: &[u8], topics: &[Bytes32]) {
+        self.logs.push(Log {
+            address: *addr,
+            data: data.to_vec(),
+            topics: topics.to_vec(),
+        });
+        self.journal.push(JournalEntry::Log);
+    }
+
+    fn access_account(&mut self, addr: &Address) -> AccessStatus {
+        if self.accessed_accounts.insert(*addr) {
+            self.journal.push(JournalEntry::AccountAccessed(*addr));
+            AccessStatus::EVMC_ACCESS_COLD
+        } else {
+            AccessStatus::EVMC_ACCESS_WARM
+        }
+    }
+
+    fn access_storage(&mut self, addr: &Address, key: &Bytes32) -> AccessStatus {
+        if self.accessed_slots.insert((*addr, *key)) {
+            self.journal.push(JournalEntry::SlotAccessed(*addr, *key));
+            AccessStatus::EVMC_ACCESS_COLD
+        } else {
+            AccessStatus::EVMC_ACCESS_WARM
+        }
+    }
+
+    fn get_transient_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32 {
+        self.transient_storage
+            .get(&(*addr, *key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn set_transient_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32) {
+        let previous = self.get_transient_storage(addr, key);
+        set_or_remove(&mut self.transient_storage, (*addr, *key), *value);
+        self.journal
+            .push(JournalEntry::TransientStorage(*addr, *key, previous));
+    }
+
+    fn get_delegate(&self, addr: &Address) -> Option<Address> {
+        delegate(&self.accounts.get(addr)?.code)
+    }
+}
+
+/// Zero values are not stored, so that they do not show up in [`Account::storage`].
+fn set_or_remove<K: Eq + Hash>(map: &mut HashMap<K, Bytes32>, key: K, value: Bytes32) {
+    if value == Bytes32::default() {
+        map.remove(&key);
+    } else {
+        map.insert(key, value);
+    }
+}
+
+/// Classifies a storage write as described in the documentation of `evmc_storage_status`, based on
+/// the value at the start of the transaction, the current value and the new value.
+fn storage_status(original: Bytes32, current: Bytes32, new: Bytes32) -> StorageStatus {
+    let zero = Bytes32::default();
+    if current == new {
+        StorageStatus::EVMC_STORAGE_ASSIGNED
+    } else if original == current {
+        if original == zero {
+            StorageStatus::EVMC_STORAGE_ADDED
+        } else if new == zero {
+            StorageStatus::EVMC_STORAGE_DELETED
+        } else {
+            StorageStatus::EVMC_STORAGE_MODIFIED
+        }
+    } else if original != zero && current == zero {
+        if new == original {
+            StorageStatus::EVMC_STORAGE_DELETED_RESTORED
+        } else {
+            StorageStatus::EVMC_STORAGE_DELETED_ADDED
+        }
+    } else if original != zero && new == zero {
+        StorageStatus::EVMC_STORAGE_MODIFIED_DELETED
+    } else if original == zero && new == zero {
+        StorageStatus::EVMC_STORAGE_ADDED_DELETED
+    } else if new == original {
+        StorageStatus::EVMC_STORAGE_MODIFIED_RESTORED
+    } else {
+        StorageStatus::EVMC_STORAGE_ASSIGNED
+    }
+}
+
+fn keccak256(data: &[u8]) -> Bytes32 {
+    Bytes32 {
+        bytes: Keccak256::digest(data).into(),
+    }
+}
+
+fn address_from_hash(hash: Bytes32) -> Address {
+    let mut addr = Address::default();
+    addr.bytes.copy_from_slice(&hash.bytes[12..]);
+    addr
+}
+
+/// `keccak256(rlp([sender, nonce]))[12..]`
+fn create_address(sender: &Address, nonce: u64) -> Address {
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+    let mut rlp = vec![0; 2];
+    rlp[1] = 0x80 + 20;
+    rlp.extend_from_slice(&sender.bytes);
+    match nonce_bytes {
+        [byte] if *byte < 0x80 => rlp.push(*byte),
+        _ => {
+            rlp.push(0x80 + nonce_bytes.len() as u8);
+            rlp.extend_from_slice(nonce_bytes);
+        }
+    }
+    rlp[0] = 0xc0 + (rlp.len() - 1) as u8;
+    address_from_hash(keccak256(&rlp))
+}
+
+/// `keccak256(0xff ++ sender ++ salt ++ keccak256(init_code))[12..]`
+fn create2_address(sender: &Address, salt: &Bytes32, init_code: &[u8]) -> Address {
+    let mut preimage = Vec::with_capacity(1 + 20 + 32 + 32);
+    preimage.push(0xff);
+    preimage.extend_from_slice(&sender.bytes);
+    preimage.extend_from_slice(&salt.bytes);
+    preimage.extend_from_slice(&keccak256(init_code).bytes);
+    address_from_hash(keccak256(&preimage))
+}
+
+#[cfg(test)]
+mod tests {
+    use evmc_vm::{
+        AccessStatus, Address, Bytes32, ExecutionMessage, ExecutionTxContext, MessageKind,
+        Revision, StatusCode, StorageStatus,
+    };
+
+    use crate::host::{
+        in_memory::{create2_address, create_address, storage_status},
+        Account, Host, InMemoryHost,
+    };
+
+    fn address(byte: u8) -> Address {
+        Address { bytes: [byte; 20] }
+    }
+
+    fn word(value: u8) -> Bytes32 {
+        let mut bytes = [0; 32];
+        bytes[31] = value;
+        Bytes32 { bytes }
+    }
+
+    fn message(kind: MessageKind, recipient: Address, input: &[u8]) -> ExecutionMessage {
+        ExecutionMessage::new(
+            kind,
+            0,
+            0,
+            1_000_000,
+            recipient,
+            address(1),
+            Some(input),
+            word(0),
+            word(0),
+            recipient,
+            None,
+        )
+    }
+
+    fn host_with_code(revision: Revision, addr: Address, code: &[u8]) -> InMemoryHost {
+        let mut host = InMemoryHost::new(revision, ExecutionTxContext::default());
+        host.set_account(
+            addr,
+            Account {
+                code: code.to_vec(),
+                ..Default::default()
+            },
+        );
+        host
+    }
+
+    #[test]
+    fn storage_status_follows_evmc_definition() {
+        // (original, current, new, status)
+        let cases = [
+            (0, 0, 0, StorageStatus::EVMC_STORAGE_ASSIGNED),
+            (0, 0, 1, StorageStatus::EVMC_STORAGE_ADDED),
+            (1, 1, 0, StorageStatus::EVMC_STORAGE_DELETED),
+            (1, 1, 2, StorageStatus::EVMC_STORAGE_MODIFIED),
+            (1, 0, 2, StorageStatus::EVMC_STORAGE_DELETED_ADDED),
+            (1, 2, 0, StorageStatus::EVMC_STORAGE_MODIFIED_DELETED),
+            (1, 0, 1, StorageStatus::EVMC_STORAGE_DELETED_RESTORED),
+            (0, 1, 0, StorageStatus::EVMC_STORAGE_ADDED_DELETED),
+            (1, 2, 1, StorageStatus::EVMC_STORAGE_MODIFIED_RESTORED),
+            (0, 1, 2, StorageStatus::EVMC_STORAGE_ASSIGNED),
+            (1, 2, 3, StorageStatus::EVMC_STORAGE_ASSIGNED),
+        ];
+        for (original, current, new, status) in cases {
+            assert_eq!(
+                storage_status(word(original), word(current), word(new)),
+                status,
+                "original {original}, current {current}, new {new}"
+            );
+        }
+    }
+
+    #[test]
+    fn set_storage_tracks_original_value_per_transaction() {
+        let mut host = InMemoryHost::new(Revision::EVMC_CANCUN, ExecutionTxContext::default());
+        let (addr, key) = (address(2), word(0));
+        assert_eq!(
+            host.set_storage(&addr, &key, &word(1)),
+            StorageStatus::EVMC_STORAGE_ADDED
+        );
+        assert_eq!(
+            host.set_storage(&addr, &key, &word(2)),
+            StorageStatus::EVMC_STORAGE_ASSIGNED
+        );
+        assert_eq!(
+            host.set_storage(&addr, &key, &word(0)),
+            StorageStatus::EVMC_STORAGE_ADDED_DELETED
+        );
+        host.set_storage(&addr, &key, &word(2));
+        host.finalize_transaction();
+        assert_eq!(
+            host.set_storage(&addr, &key, &word(3)),
+            StorageStatus::EVMC_STORAGE_MODIFIED
+        );
+    }
+
+    #[test]
+    fn revert_to_snapshot_undoes_changes() {
+        let mut host = InMemoryHost::new(Revision::EVMC_CANCUN, ExecutionTxContext::default());
+        let (addr, key) = (address(2), word(0));
+        host.set_storage(&addr, &key, &word(1));
+        let before = host.account(&addr).cloned();
+
+        let snapshot = host.snapshot();
+        host.set_storage(&addr, &key, &word(2));
+        host.set_storage(&address(3), &key, &word(2));
+        host.set_transient_storage(&addr, &key, &word(2));
+        host.access_account(&addr);
+        host.access_storage(&addr, &key);
+        host.emit_log(&addr, &[1], &[]);
+        host.revert_to_snapshot(snapshot);
+
+        assert_eq!(host.account(&addr).cloned(), before);
+        assert_eq!(host.account(&address(3)), None);
+        assert_eq!(host.get_transient_storage(&addr, &key), word(0));
+        assert!(host.logs().is_empty());
+        assert_eq!(host.access_account(&addr), AccessStatus::EVMC_ACCESS_COLD);
+        assert_eq!(
+            host.access_storage(&addr, &key),
+            AccessStatus::EVMC_ACCESS_COLD
+        );
+    }
+
+    #[test]
+    fn create_addresses() {
+        let sender = Address {
+            bytes: [
+                0x6a, 0xc7, 0xea, 0x33, 0xf8, 0x83, 0x1e, 0xa9, 0xdc, 0xc5, 0x33, 0x93, 0xaa, 0xa8,
+                0x8b, 0x25, 0xa7, 0x85, 0xdb, 0xf0,
+            ],
+        };
+        assert_eq!(
+            create_address(&sender, 0).bytes,
+            [
+                0xcd, 0x23, 0x4a, 0x47, 0x1b, 0x72, 0xba, 0x2f, 0x1c, 0xcf, 0x0a, 0x70, 0xfc, 0xab,
+                0xa6, 0x48, 0xa5, 0xee, 0xcd, 0x8d
+            ]
+        );
+        assert_eq!(
+            create_address(&sender, 1).bytes,
+            [
+                0x34, 0x3c, 0x43, 0xa3, 0x7d, 0x37, 0xdf, 0xf0, 0x8a, 0xe8, 0xc4, 0xa1, 0x15, 0x44,
+                0xc7, 0x18, 0xab, 0xb4, 0xfc, 0xf8
+            ]
+        );
+        // example 0 of EIP-1014
+        assert_eq!(
+            create2_address(&Address::default(), &Bytes32::default(), &[0x00]).bytes,
+            [
+                0x4d, 0x1a, 0x2e, 0x2b, 0xb4, 0xf8, 0x8f, 0x02, 0x50, 0xf2, 0x6f, 0xff, 0xf0, 0x98,
+                0xb0, 0xb3, 0x0b, 0x26, 0xbf, 0x38
+            ]
+        );
+    }
+
+    #[test]
+    fn get_delegate_only_accepts_delegation_designators() {
+        let (addr, delegate) = (address(2), address(3));
+        let designator = [&[0xef, 0x01, 0x00][..], &delegate.bytes].concat();
+        let cases = [
+            (designator.clone(), Some(delegate)),
+            (designator[..22].to_vec(), None),
+            ([&designator[..], &[0]].concat(), None),
+            ([&[0xef, 0x01, 0x01][..], &delegate.bytes].concat(), None),
+        ];
+        for (code, expected) in cases {
+            let host = host_with_code(Revision::EVMC_PRAGUE, addr, &code);
+            assert_eq!(host.get_delegate(&addr), expected);
+        }
+    }
+
+    #[test]
+    fn call_executes_code_and_reverts_state_on_failure() {
+        let addr = address(2);
+        let code = [
+            0x60, 0x01, // PUSH1 1
+            0x60, 0x00, // PUSH1 0
+            0x55, // SSTORE
+            0x60, 0x00, // PUSH1 0
+            0x35, // CALLDATALOAD
+            0x60, 0x0c, // PUSH1 12
+            0x57, // JUMPI
+            0x00, // STOP
+            0x5b, // JUMPDEST
+            0x60, 0x00, // PUSH1 0
+            0x80, // DUP1
+            0xfd, // REVERT
+        ];
+        let mut host = host_with_code(Revision::EVMC_CANCUN, addr, &code);
+
+        let result = host.call(&message(MessageKind::EVMC_CALL, addr, &word(1).bytes));
+        assert_eq!(result.status_code(), StatusCode::EVMC_REVERT);
+        assert_eq!(host.get_storage(&addr, &word(0)), word(0));
+
+        let result = host.call(&message(MessageKind::EVMC_CALL, addr, &word(0).bytes));
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        assert_eq!(host.get_storage(&addr, &word(0)), word(1));
+    }
+
+    #[test]
+    fn begin_transaction_warms_recipient_and_access_list() {
+        let mut host = InMemoryHost::new(Revision::EVMC_CANCUN, ExecutionTxContext::default());
+        host.begin_transaction(&address(2), &[(address(3), vec![word(4)])]);
+        for addr in [address(0), address(2), address(3)] {
+            assert_eq!(host.access_account(&addr), AccessStatus::EVMC_ACCESS_WARM);
+        }
+        assert_eq!(
+            host.access_account(&address(4)),
+            AccessStatus::EVMC_ACCESS_COLD
+        );
+        assert_eq!(
+            host.access_storage(&address(3), &word(4)),
+            AccessStatus::EVMC_ACCESS_WARM
+        );
+        assert_eq!(
+            host.access_storage(&address(3), &word(5)),
+            AccessStatus::EVMC_ACCESS_COLD
+        );
+
+        // Starting a transaction is not journaled, so reverting keeps the accounts warm.
+        host.revert_to_snapshot(0);
+        assert_eq!(
+            host.access_account(&address(2)),
+            AccessStatus::EVMC_ACCESS_WARM
+        );
+    }
+
+    #[test]
+    fn nested_call_into_transaction_recipient_is_warm() {
+        let to = address(2);
+        let callee = address(3);
+        let call = |addr: Address, args_size: u8| {
+            let mut code = vec![
+                0x60, 0x00, // PUSH1 0 (ret size)
+                0x60, 0x00, // PUSH1 0 (ret offset)
+                0x60, args_size, // PUSH1 args_size
+                0x60, 0x00, // PUSH1 0 (args offset)
+                0x60, 0x00, // PUSH1 0 (value)
+                0x73, // PUSH20 addr
+            ];
+            code.extend(addr.bytes);
+            code.extend([
+                0x5a, // GAS
+                0xf1, // CALL
+                0x00, // STOP
+            ]);
+            code
+        };
+        // The recipient calls the callee, which calls back into the recipient with call data, so
+        // that the recipient stops.
+        let mut to_code = vec![
+            0x36, // CALLDATASIZE
+            0x60, 0x26, // PUSH1 38
+            0x57, // JUMPI
+        ];
+        to_code.extend(call(callee, 0));
+        to_code.extend([
+            0x5b, // JUMPDEST
+            0x00, // STOP
+        ]);
+        assert_eq!(to_code[38], 0x5b);
+
+        let gas_used = |begin_transaction: bool| {
+            let mut host = host_with_code(Revision::EVMC_CANCUN, to, &to_code);
+            host.set_account(
+                callee,
+                Account {
+                    code: call(to, 1),
+                    ..Default::default()
+                },
+            );
+            if begin_transaction {
+                host.begin_transaction(&to, &[]);
+            }
+            let message = message(MessageKind::EVMC_CALL, to, &[]);
+            let result = host.call(&message);
+            assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+            message.gas() - result.gas_left()
+        };
+        // Cold account access (2600) vs. warm account access (100).
+        assert_eq!(gas_used(false) - gas_used(true), 2500);
+    }
+
+    #[test]
+    fn create_deploys_code() {
+        let init_code = [
+            0x60, 0x2a, // PUSH1 42
+            0x60, 0x00, // PUSH1 0
+            0x53, // MSTORE8
+            0x60, 0x01, // PUSH1 1
+            0x60, 0x00, // PUSH1 0
+            0xf3, // RETURN
+        ];
+        let mut host = InMemoryHost::new(Revision::EVMC_CANCUN, ExecutionTxContext::default());
+        let result = host.call(&message(
+            MessageKind::EVMC_CREATE,
+            Address::default(),
+            &init_code,
+        ));
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        let addr = create_address(&address(1), 0);
+        assert_eq!(result.create_address(), Some(&addr));
+        let account = host.account(&addr).unwrap();
+        assert_eq!(account.code, [42]);
+        assert_eq!(account.nonce, 1);
+        assert_eq!(host.account(&address(1)).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn legacy_create_fails_for_eof_init_code_from_osaka() {
+        let init_code = [0xef, 0x00, 0x01];
+        for (revision, status_code) in [
+            (Revision::EVMC_PRAGUE, StatusCode::EVMC_INVALID_INSTRUCTION),
+            (Revision::EVMC_OSAKA, StatusCode::EVMC_FAILURE),
+        ] {
+            let mut host = InMemoryHost::new(revision, ExecutionTxContext::default());
+            let result = host.call(&message(
+                MessageKind::EVMC_CREATE,
+                Address::default(),
+                &init_code,
+            ));
+            assert_eq!(result.status_code(), status_code, "{revision:?}");
+            assert_eq!(host.account(&address(1)).unwrap().nonce, 1);
+        }
+    }
+
+    #[test]
+    fn selfdestruct_deletes_account_depending_on_revision() {
+        let addr = address(2);
+        let code = [
+            0x60, 0x03, // PUSH1 3
+            0xff, // SELFDESTRUCT
+        ];
+        for (revision, deleted) in [
+            (Revision::EVMC_SHANGHAI, true),
+            (Revision::EVMC_CANCUN, false),
+        ] {
+            let mut host = host_with_code(revision, addr, &code);
+            let result = host.call(&message(MessageKind::EVMC_CALL, addr, &[]));
+            assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+            host.finalize_transaction();
+            assert_eq!(host.account(&addr).is_none(), deleted, "{revision:?}");
+        }
+    }
+}
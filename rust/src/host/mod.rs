@@ -0,0 +1,225 @@
+use evmc_vm::{
+    AccessStatus, Address, Bytes32, ExecutionContext, ExecutionMessage, ExecutionResult,
+    ExecutionTxContext, StorageStatus, Uint256,
+};
+
+mod in_memory;
+
+pub use in_memory::{Account, InMemoryHost, Log};
+
+/// The interface through which the interpreter accesses the world state and the transaction. It
+/// mirrors the EVMC host interface, which is implemented by [`ExecutionContext`], but allows any
+/// Rust type to act as host.
+pub trait Host {
+    fn account_exists(&self, addr: &Address) -> bool;
+
+    fn get_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32;
+
+    fn set_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32) -> StorageStatus;
+
+    fn get_balance(&self, addr: &Address) -> Uint256;
+
+    fn get_code_size(&self, addr: &Address) -> usize;
+
+    fn get_code_hash(&self, addr: &Address) -> Bytes32;
+
+    /// Copies the code of `addr` starting at `offset` into `buffer` and returns the number of
+    /// bytes written.
+    fn copy_code(&self, addr: &Address, offset: usize, buffer: &mut [u8]) -> usize;
+
+    /// Returns whether the account was not already marked for destruction before.
+    fn selfdestruct(&mut self, addr: &Address, beneficiary: &Address) -> bool;
+
+    fn call(&mut self, message: &ExecutionMessage) -> ExecutionResult;
+
+    fn get_tx_context(&self) -> ExecutionTxContext;
+
+    fn get_block_hash(&self, number: i64) -> Bytes32;
+
+    fn emit_log(&mut self, addr: &Address, data: &[u8], topics: &[Bytes32]);
+
+    fn access_account(&mut self, addr: &Address) -> AccessStatus;
+
+    fn access_storage(&mut self, addr: &Address, key: &Bytes32) -> AccessStatus;
+
+    fn get_transient_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32;
+
+    fn set_transient_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32);
+
+    /// Returns the address the code of `addr` delegates to if it is a delegation designator
+    /// (EIP-7702). The provided implementation needs a single [`Host::copy_code`].
+    fn get_delegate(&self, addr: &Address) -> Option<Address> {
+        let mut code = [0; DELEGATION_LEN + 1];
+        if self.copy_code(addr, 0, &mut code) != DELEGATION_LEN {
+            return None;
+        }
+        delegate(&code[..DELEGATION_LEN])
+    }
+}
+
+/// The prefix of a delegation designator, which is followed by the address of the account whose
+/// code is executed instead.
+const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+const DELEGATION_LEN: usize = DELEGATION_PREFIX.len() + 20;
+
+/// Returns the address `code` delegates to if it is a delegation designator.
+fn delegate(code: &[u8]) -> Option<Address> {
+    let delegate = code.strip_prefix(&DELEGATION_PREFIX)?;
+    Some(Address {
+        bytes: delegate.try_into().ok()?,
+    })
+}
+
+impl Host for ExecutionContext<'_> {
+    fn account_exists(&self, addr: &Address) -> bool {
+        ExecutionContext::account_exists(self, addr)
+    }
+
+    fn get_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32 {
+        ExecutionContext::get_storage(self, addr, key)
+    }
+
+    fn set_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32) -> StorageStatus {
+        ExecutionContext::set_storage(self, addr, key, value)
+    }
+
+    fn get_balance(&self, addr: &Address) -> Uint256 {
+        ExecutionContext::get_balance(self, addr)
+    }
+
+    fn get_code_size(&self, addr: &Address) -> usize {
+        ExecutionContext::get_code_size(self, addr)
+    }
+
+    fn get_code_hash(&self, addr: &Address) -> Bytes32 {
+        ExecutionContext::get_code_hash(self, addr)
+    }
+
+    fn copy_code(&self, addr: &Address, offset: usize, buffer: &mut [u8]) -> usize {
+        ExecutionContext::copy_code(self, addr, offset, buffer)
+    }
+
+    fn selfdestruct(&mut self, addr: &Address, beneficiary: &Address) -> bool {
+        ExecutionContext::selfdestruct(self, addr, beneficiary)
+    }
+
+    fn call(&mut self, message: &ExecutionMessage) -> ExecutionResult {
+        ExecutionContext::call(self, message)
+    }
+
+    fn get_tx_context(&self) -> ExecutionTxContext {
+        ExecutionContext::get_tx_context(self)
+    }
+
+    fn get_block_hash(&self, number: i64) -> Bytes32 {
+        ExecutionContext::get_block_hash(self, number)
+    }
+
+    fn emit_log(&mut self, addr: &Address, data: &[u8], topics: &[Bytes32]) {
+        ExecutionContext::emit_log(self, addr, data, topics)
+    }
+
+    fn access_account(&mut self, addr: &Address) -> AccessStatus {
+        ExecutionContext::access_account(self, addr)
+    }
+
+    fn access_storage(&mut self, addr: &Address, key: &Bytes32) -> AccessStatus {
+        ExecutionContext::access_storage(self, addr, key)
+    }
+
+    fn get_transient_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32 {
+        ExecutionContext::get_transient_storage(self, addr, key)
+    }
+
+    fn set_transient_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32) {
+        ExecutionContext::set_transient_storage(self, addr, key, value)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use evmc_vm::{
+        AccessStatus, Address, Bytes32, ExecutionMessage, ExecutionResult, ExecutionTxContext,
+        StorageStatus, Uint256,
+    };
+
+    use crate::host::{Host, InMemoryHost};
+
+    /// An [`InMemoryHost`] whose calls and creates are not executed but return the result of
+    /// `call`, e.g. to simulate a misbehaving client.
+    pub(crate) struct CallResultHost {
+        pub host: InMemoryHost,
+        pub call: fn(&ExecutionMessage) -> ExecutionResult,
+    }
+
+    impl Host for CallResultHost {
+        fn account_exists(&self, addr: &Address) -> bool {
+            self.host.account_exists(addr)
+        }
+
+        fn get_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32 {
+            self.host.get_storage(addr, key)
+        }
+
+        fn set_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32) -> StorageStatus {
+            self.host.set_storage(addr, key, value)
+        }
+
+        fn get_balance(&self, addr: &Address) -> Uint256 {
+            self.host.get_balance(addr)
+        }
+
+        fn get_code_size(&self, addr: &Address) -> usize {
+            self.host.get_code_size(addr)
+        }
+
+        fn get_code_hash(&self, addr: &Address) -> Bytes32 {
+            self.host.get_code_hash(addr)
+        }
+
+        fn copy_code(&self, addr: &Address, offset: usize, buffer: &mut [u8]) -> usize {
+            self.host.copy_code(addr, offset, buffer)
+        }
+
+        fn selfdestruct(&mut self, addr: &Address, beneficiary: &Address) -> bool {
+            self.host.selfdestruct(addr, beneficiary)
+        }
+
+        fn call(&mut self, message: &ExecutionMessage) -> ExecutionResult {
+            (self.call)(message)
+        }
+
+        fn get_tx_context(&self) -> ExecutionTxContext {
+            self.host.get_tx_context()
+        }
+
+        fn get_block_hash(&self, number: i64) -> Bytes32 {
+            self.host.get_block_hash(number)
+        }
+
+        fn emit_log(&mut self, addr: &Address, data: &[u8], topics: &[Bytes32]) {
+            self.host.emit_log(addr, data, topics)
+        }
+
+        fn access_account(&mut self, addr: &Address) -> AccessStatus {
+            self.host.access_account(addr)
+        }
+
+        fn access_storage(&mut self, addr: &Address, key: &Bytes32) -> AccessStatus {
+            self.host.access_storage(addr, key)
+        }
+
+        fn get_transient_storage(&self, addr: &Address, key: &Bytes32) -> Bytes32 {
+            self.host.get_transient_storage(addr, key)
+        }
+
+        fn set_transient_storage(&mut self, addr: &Address, key: &Bytes32, value: &Bytes32) {
+            self.host.set_transient_storage(addr, key, value)
+        }
+
+        fn get_delegate(&self, addr: &Address) -> Option<Address> {
+            self.host.get_delegate(addr)
+        }
+    }
+}
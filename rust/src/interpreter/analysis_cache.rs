@@ -0,0 +1,133 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use evmc_vm::Revision;
+
+use crate::{
+    interpreter::{eof::Container, sha3_cache::keccak256, CodeAnalysis},
+    types::u256,
+    utils::LruCache,
+};
+
+const CACHE_SIZE: usize = 1 << 16;
+
+/// Caches the [`CodeAnalysis`] of recently executed code so that it does not have to be recomputed
+/// on every execution.
+#[derive(Debug, Default)]
+pub struct AnalysisCache {
+    /// Keyed by the code hash and whether the code is analyzed as EOF container, which depends on
+    /// the revision.
+    cache: LruCache<(u256, bool), Arc<CodeAnalysis>, CACHE_SIZE>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AnalysisCache {
+    /// Returns the analysis of `code`. The cache is keyed by `code_hash` if the host provided one,
+    /// otherwise by the keccak hash of the code.
+    pub fn get_or_analyze(
+        &self,
+        code_hash: Option<u256>,
+        code: &[u8],
+        revision: Revision,
+    ) -> Arc<CodeAnalysis> {
+        let code_hash = match code_hash {
+            Some(code_hash) if code_hash != u256::ZERO => code_hash,
+            _ => keccak256(code),
+        };
+        let key = (
+            code_hash,
+            revision >= Revision::EVMC_OSAKA && Container::is_eof(code),
+        );
+
+        if let Some(analysis) = self.cache.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return analysis;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        self.cache
+            .insert(key, Arc::new(CodeAnalysis::for_revision(code, revision)))
+    }
+
+    #[allow(dead_code)]
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    #[allow(dead_code)]
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use evmc_vm::Revision;
+
+    use crate::{
+        interpreter::{sha3_cache::keccak256, AnalysisCache},
+        types::{u256, Opcode},
+    };
+
+    #[test]
+    fn analysis_cache_counts_hits_and_misses() {
+        let cache = AnalysisCache::default();
+        let code = [Opcode::Push1 as u8, 0x01, Opcode::Stop as u8];
+
+        let first = cache.get_or_analyze(Some(1u64.into()), &code, Revision::EVMC_CANCUN);
+        let second = cache.get_or_analyze(Some(1u64.into()), &code, Revision::EVMC_CANCUN);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 1);
+
+        let third = cache.get_or_analyze(Some(u256::MAX), &code, Revision::EVMC_CANCUN);
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 2);
+    }
+
+    #[test]
+    fn analysis_cache_keys_code_without_hash_by_keccak() {
+        let cache = AnalysisCache::default();
+        let code = [Opcode::Push1 as u8, 0x01, Opcode::Stop as u8];
+
+        let first = cache.get_or_analyze(None, &code, Revision::EVMC_CANCUN);
+        let second = cache.get_or_analyze(None, &code, Revision::EVMC_CANCUN);
+        let third = cache.get_or_analyze(Some(keccak256(&code)), &code, Revision::EVMC_CANCUN);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(&first, &third));
+        assert_eq!(cache.hits(), 2);
+        assert_eq!(cache.misses(), 1);
+    }
+
+    #[test]
+    fn analysis_cache_ignores_zero_code_hash() {
+        let cache = AnalysisCache::default();
+        let add = [Opcode::Add as u8];
+        let mul = [Opcode::Mul as u8];
+
+        let first = cache.get_or_analyze(Some(u256::ZERO), &add, Revision::EVMC_CANCUN);
+        let second = cache.get_or_analyze(Some(u256::ZERO), &mul, Revision::EVMC_CANCUN);
+        let third = cache.get_or_analyze(Some(u256::ZERO), &add, Revision::EVMC_CANCUN);
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(&first, &third));
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 2);
+    }
+
+    #[test]
+    fn analysis_cache_separates_legacy_and_eof_analysis() {
+        let cache = AnalysisCache::default();
+        let code = [0xef, 0x00];
+
+        let legacy = cache.get_or_analyze(Some(1u64.into()), &code, Revision::EVMC_PRAGUE);
+        let eof = cache.get_or_analyze(Some(1u64.into()), &code, Revision::EVMC_OSAKA);
+        assert!(!Arc::ptr_eq(&legacy, &eof));
+        assert!(legacy.container().is_ok());
+        assert!(eof.container().is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use evmc_vm::Revision;
+
+use crate::{
+    interpreter::{
+        checks::check_min_revision, error::FailureReason, gas::consume_gas,
+        stack::STACK_SIZE_LIMIT, Stack,
+    },
+    types::Opcode,
+};
+
+/// The part of the cost and the stack requirements of an opcode which does not depend on the
+/// state of the execution.
+///
+/// Opcodes with a static part are checked either right before they are executed or, if they are
+/// part of a basic block, once for the whole block. Their handlers only check and consume the
+/// dynamic part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticInfo {
+    gas: u64,
+    min_revision: Revision,
+    /// Number of stack elements accessed by this opcode.
+    min_stack_len: usize,
+    /// Difference of the stack length after and before this opcode.
+    stack_delta: isize,
+    /// Whether this opcode has to be the last one in a basic block. This is the case for opcodes
+    /// which change the control flow, read `gas_left` or consume dynamic gas.
+    ends_block: bool,
+}
+
+impl StaticInfo {
+    const fn new(gas: u64, min_stack_len: usize, stack_delta: isize) -> Self {
+        Self {
+            gas,
+            min_revision: Revision::EVMC_FRONTIER,
+            min_stack_len,
+            stack_delta,
+            ends_block: false,
+        }
+    }
+
+    const fn min_revision(mut self, min_revision: Revision) -> Self {
+        self.min_revision = min_revision;
+        self
+    }
+
+    const fn ends_block(mut self) -> Self {
+        self.ends_block = true;
+        self
+    }
+
+    /// Returns `None` for opcodes whose cost depends on the revision or which check and consume
+    /// all gas in their handler.
+    #[inline(always)]
+    pub fn of(op: Opcode) -> Option<Self> {
+        let info = match op {
+            Opcode::Add
+            | Opcode::Sub
+            | Opcode::Lt
+            | Opcode::Gt
+            | Opcode::SLt
+            | Opcode::SGt
+            | Opcode::Eq
+            | Opcode::And
+            | Opcode::Or
+            | Opcode::Xor
+            | Opcode::Byte => Self::new(3, 2, -1),
+            Opcode::Shl | Opcode::Shr | Opcode::Sar => {
+                Self::new(3, 2, -1).min_revision(Revision::EVMC_CONSTANTINOPLE)
+            }
+            Opcode::Mul
+            | Opcode::Div
+            | Opcode::SDiv
+            | Opcode::Mod
+            | Opcode::SMod
+            | Opcode::SignExtend => Self::new(5, 2, -1),
+            Opcode::AddMod | Opcode::MulMod => Self::new(8, 3, -2),
+            Opcode::Exp => Self::new(10, 2, -1).ends_block(),
+            Opcode::IsZero | Opcode::Not | Opcode::CallDataLoad => Self::new(3, 1, 0),
+            Opcode::Sha3 => Self::new(30, 2, -1).ends_block(),
+            Opcode::Address
+            | Opcode::Origin
+            | Opcode::Caller
+            | Opcode::CallValue
+            | Opcode::CallDataSize
+            | Opcode::CodeSize
+            | Opcode::GasPrice
+            | Opcode::Coinbase
+            | Opcode::Timestamp
+            | Opcode::Number
+            | Opcode::PrevRandao
+            | Opcode::GasLimit
+            | Opcode::Pc
+            | Opcode::MSize => Self::new(2, 0, 1),
+            Opcode::ReturnDataSize => Self::new(2, 0, 1).min_revision(Revision::EVMC_BYZANTIUM),
+            Opcode::ChainId => Self::new(2, 0, 1).min_revision(Revision::EVMC_ISTANBUL),
+            Opcode::Push0 => Self::new(2, 0, 1).min_revision(Revision::EVMC_SHANGHAI),
+            Opcode::DataLoad => Self::new(4, 1, 0).min_revision(Revision::EVMC_OSAKA),
+            Opcode::DataLoadN => Self::new(3, 0, 1).min_revision(Revision::EVMC_OSAKA),
+            Opcode::DataSize => Self::new(2, 0, 1).min_revision(Revision::EVMC_OSAKA),
+            Opcode::ReturnDataLoad => Self::new(3, 1, 0).min_revision(Revision::EVMC_OSAKA),
+            Opcode::CallDataCopy | Opcode::CodeCopy => Self::new(3, 3, -3).ends_block(),
+            Opcode::ReturnDataCopy => Self::new(3, 3, -3)
+                .min_revision(Revision::EVMC_BYZANTIUM)
+                .ends_block(),
+            Opcode::DataCopy => Self::new(3, 3, -3)
+                .min_revision(Revision::EVMC_OSAKA)
+                .ends_block(),
+            Opcode::BlockHash => Self::new(20, 1, 0),
+            Opcode::SelfBalance => Self::new(5, 0, 1).min_revision(Revision::EVMC_ISTANBUL),
+            Opcode::BaseFee => Self::new(2, 0, 1).min_revision(Revision::EVMC_LONDON),
+            Opcode::BlobHash => Self::new(3, 1, 0).min_revision(Revision::EVMC_CANCUN),
+            Opcode::BlobBaseFee => Self::new(2, 0, 1).min_revision(Revision::EVMC_CANCUN),
+            Opcode::Pop => Self::new(2, 1, -1),
+            Opcode::MLoad => Self::new(3, 1, 0).ends_block(),
+            Opcode::MStore | Opcode::MStore8 => Self::new(3, 2, -2).ends_block(),
+            Opcode::Jump => Self::new(8, 1, -1).ends_block(),
+            Opcode::JumpI => Self::new(10, 2, -2).ends_block(),
+            Opcode::Gas => Self::new(2, 0, 1).ends_block(),
+            Opcode::JumpDest => Self::new(1, 0, 0),
+            Opcode::RJump => Self::new(2, 0, 0)
+                .min_revision(Revision::EVMC_OSAKA)
+                .ends_block(),
+            Opcode::RJumpI | Opcode::RJumpV => Self::new(4, 1, -1)
+                .min_revision(Revision::EVMC_OSAKA)
+                .ends_block(),
+            Opcode::TLoad => Self::new(100, 1, 0).min_revision(Revision::EVMC_CANCUN),
+            Opcode::MCopy => Self::new(3, 3, -3)
+                .min_revision(Revision::EVMC_CANCUN)
+                .ends_block(),
+            Opcode::Push1
+            | Opcode::Push2
+            | Opcode::Push3
+            | Opcode::Push4
+            | Opcode::Push5
+            | Opcode::Push6
+            | Opcode::Push7
+            | Opcode::Push8
+            | Opcode::Push9
+            | Opcode::Push10
+            | Opcode::Push11
+            | Opcode::Push12
+            | Opcode::Push13
+            | Opcode::Push14
+            | Opcode::Push15
+            | Opcode::Push16
+            | Opcode::Push17
+            | Opcode::Push18
+            | Opcode::Push19
+            | Opcode::Push20
+            | Opcode::Push21
+            | Opcode::Push22
+            | Opcode::Push23
+            | Opcode::Push24
+            | Opcode::Push25
+            | Opcode::Push26
+            | Opcode::Push27
+            | Opcode::Push28
+            | Opcode::Push29
+            | Opcode::Push30
+            | Opcode::Push31
+            | Opcode::Push32 => Self::new(3, 0, 1),
+            Opcode::Dup1 => Self::new(3, 1, 1),
+            Opcode::Dup2 => Self::new(3, 2, 1),
+            Opcode::Dup3 => Self::new(3, 3, 1),
+            Opcode::Dup4 => Self::new(3, 4, 1),
+            Opcode::Dup5 => Self::new(3, 5, 1),
+            Opcode::Dup6 => Self::new(3, 6, 1),
+            Opcode::Dup7 => Self::new(3, 7, 1),
+            Opcode::Dup8 => Self::new(3, 8, 1),
+            Opcode::Dup9 => Self::new(3, 9, 1),
+            Opcode::Dup10 => Self::new(3, 10, 1),
+            Opcode::Dup11 => Self::new(3, 11, 1),
+            Opcode::Dup12 => Self::new(3, 12, 1),
+            Opcode::Dup13 => Self::new(3, 13, 1),
+            Opcode::Dup14 => Self::new(3, 14, 1),
+            Opcode::Dup15 => Self::new(3, 15, 1),
+            Opcode::Dup16 => Self::new(3, 16, 1),
+            Opcode::Swap1 => Self::new(3, 2, 0),
+            Opcode::Swap2 => Self::new(3, 3, 0),
+            Opcode::Swap3 => Self::new(3, 4, 0),
+            Opcode::Swap4 => Self::new(3, 5, 0),
+            Opcode::Swap5 => Self::new(3, 6, 0),
+            Opcode::Swap6 => Self::new(3, 7, 0),
+            Opcode::Swap7 => Self::new(3, 8, 0),
+            Opcode::Swap8 => Self::new(3, 9, 0),
+            Opcode::Swap9 => Self::new(3, 10, 0),
+            Opcode::Swap10 => Self::new(3, 11, 0),
+            Opcode::Swap11 => Self::new(3, 12, 0),
+            Opcode::Swap12 => Self::new(3, 13, 0),
+            Opcode::Swap13 => Self::new(3, 14, 0),
+            Opcode::Swap14 => Self::new(3, 15, 0),
+            Opcode::Swap15 => Self::new(3, 16, 0),
+            Opcode::Swap16 => Self::new(3, 17, 0),
+            _ => return None,
+        };
+        Some(info)
+    }
+
+    /// Checks the revision, consumes the static gas and checks the stack bounds in the same order
+    /// as a handler which does all checks itself.
+    #[inline(always)]
+    pub fn check(
+        &self,
+        revision: Revision,
+        stack: &Stack,
+        gas_left: &mut u64,
+    ) -> Result<(), FailureReason> {
+        check_min_revision(self.min_revision, revision)?;
+        consume_gas(gas_left, self.gas)?;
+        stack.check_bounds(self.min_stack_len, self.stack_delta.max(0) as usize)
+    }
+
+    pub fn is_block_end(&self) -> bool {
+        self.ends_block
+    }
+}
+
+/// The accumulated [`StaticInfo`] of a basic block.
+///
+/// A basic block is a sequence of opcodes with static info which is only entered at its first
+/// opcode and which is executed completely unless the execution fails. Blocks start at the
+/// beginning of the code, at `JUMPDEST`s, at the targets of relative jumps and calls in EOF code and
+/// after opcodes which end a block or have no static info. Because `GAS` ends its block, the gas it
+/// reports is the same as with per opcode checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInfo {
+    gas: u64,
+    min_revision: Revision,
+    /// Minimum stack length at the start of the block so that no opcode underflows.
+    min_stack_len: usize,
+    /// Maximum growth of the stack relative to the start of the block.
+    max_stack_growth: usize,
+    /// Current stack length relative to the start of the block. Only used during the analysis.
+    stack_len: isize,
+}
+
+impl BlockInfo {
+    pub fn new() -> Self {
+        Self {
+            gas: 0,
+            min_revision: Revision::EVMC_FRONTIER,
+            min_stack_len: 0,
+            max_stack_growth: 0,
+            stack_len: 0,
+        }
+    }
+
+    pub fn add(&mut self, info: &StaticInfo) {
+        self.gas += info.gas;
+        if info.min_revision > self.min_revision {
+            self.min_revision = info.min_revision;
+        }
+        self.min_stack_len = self
+            .min_stack_len
+            .max((info.min_stack_len as isize - self.stack_len).max(0) as usize);
+        self.stack_len += info.stack_delta;
+        self.max_stack_growth = self.max_stack_growth.max(self.stack_len.max(0) as usize);
+    }
+
+    /// Consumes the static gas of the whole block if the whole block can be executed without
+    /// running out of gas and without stack underflows or overflows. If this is the case, `true`
+    /// is returned and the static checks of the individual opcodes can be skipped. Otherwise
+    /// nothing is consumed and the opcodes have to be checked individually, so that an
+    /// execution which fails in the middle of the block fails at the same opcode.
+    #[inline(always)]
+    pub fn try_consume(&self, revision: Revision, stack: &Stack, gas_left: &mut u64) -> bool {
+        if revision < self.min_revision
+            || *gas_left < self.gas
+            || stack.len() < self.min_stack_len
+            || stack.len() + self.max_stack_growth > STACK_SIZE_LIMIT
+        {
+            return false;
+        }
+        *gas_left -= self.gas;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evmc_vm::Revision;
+
+    use crate::{
+        interpreter::{
+            blocks::{BlockInfo, StaticInfo},
+            Stack,
+        },
+        types::{u256, Opcode},
+    };
+
+    fn block_info(ops: &[Opcode]) -> BlockInfo {
+        let mut block = BlockInfo::new();
+        for op in ops {
+            block.add(&StaticInfo::of(*op).unwrap());
+        }
+        block
+    }
+
+    #[test]
+    fn block_info_accumulates_gas_and_stack_bounds() {
+        let block = block_info(&[Opcode::Push1, Opcode::Push1, Opcode::Add, Opcode::Dup1]);
+        assert_eq!(block.gas, 12);
+        assert_eq!(block.min_stack_len, 0);
+        assert_eq!(block.max_stack_growth, 2);
+
+        let block = block_info(&[Opcode::Add, Opcode::Swap2, Opcode::Push0]);
+        assert_eq!(block.gas, 8);
+        assert_eq!(block.min_revision, Revision::EVMC_SHANGHAI);
+        assert_eq!(block.min_stack_len, 4);
+        assert_eq!(block.max_stack_growth, 0);
+    }
+
+    #[test]
+    fn block_info_try_consume() {
+        let block = block_info(&[Opcode::Pop, Opcode::Push1, Opcode::Push1]);
+        let stack = Stack::new(vec![u256::ZERO]);
+
+        let mut gas_left = 8;
+        assert!(block.try_consume(Revision::EVMC_CANCUN, &stack, &mut gas_left));
+        assert_eq!(gas_left, 0);
+
+        let mut gas_left = 7;
+        assert!(!block.try_consume(Revision::EVMC_CANCUN, &stack, &mut gas_left));
+        assert_eq!(gas_left, 7);
+
+        let mut gas_left = 8;
+        assert!(!block.try_consume(
+            Revision::EVMC_CANCUN,
+            &Stack::new(Vec::new()),
+            &mut gas_left
+        ));
+        assert_eq!(gas_left, 8);
+
+        let stack = Stack::new(vec![u256::ZERO; 1024]);
+        assert!(!block.try_consume(Revision::EVMC_CANCUN, &stack, &mut gas_left));
+        assert_eq!(gas_left, 8);
+    }
+}
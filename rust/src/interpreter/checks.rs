@@ -1,15 +1,14 @@
-use evmc_vm::{ExecutionMessage, MessageFlags, Revision, StatusCode, StepStatusCode};
+use evmc_vm::{ExecutionMessage, MessageFlags, Revision};
+
+use crate::interpreter::error::FailureReason;
 
 #[inline(always)]
 pub(super) fn check_min_revision(
     min_revision: Revision,
     revision: Revision,
-) -> Result<(), (StepStatusCode, StatusCode)> {
+) -> Result<(), FailureReason> {
     if revision < min_revision {
-        return Err((
-            StepStatusCode::EVMC_STEP_FAILED,
-            StatusCode::EVMC_UNDEFINED_INSTRUCTION,
-        ));
+        return Err(FailureReason::UndefinedInstruction);
     }
     Ok(())
 }
@@ -18,14 +17,11 @@ pub(super) fn check_min_revision(
 pub(super) fn check_not_read_only(
     message: &ExecutionMessage,
     revision: Revision,
-) -> Result<(), (StepStatusCode, StatusCode)> {
+) -> Result<(), FailureReason> {
     if revision >= Revision::EVMC_BYZANTIUM {
         let read_only = message.flags() == MessageFlags::EVMC_STATIC as u32;
         if read_only {
-            return Err((
-                StepStatusCode::EVMC_STEP_FAILED,
-                StatusCode::EVMC_STATIC_MODE_VIOLATION,
-            ));
+            return Err(FailureReason::StaticModeViolation);
         }
     }
     Ok(())
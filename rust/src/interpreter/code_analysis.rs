@@ -0,0 +1,633 @@
+use std::{cmp::min, mem, sync::OnceLock};
+
+use evmc_vm::Revision;
+
+use crate::{
+    interpreter::{
+        blocks::{BlockInfo, StaticInfo},
+        eof::{self, Container, EofError},
+        jump_dests::JumpDests,
+        super_instructions::SuperInstruction,
+    },
+    types::{code_byte_type, u256, CodeByteType, Opcode},
+};
+
+/// A pre-decoded instruction.
+///
+/// There is one instruction per opcode, immediates are not part of the instruction stream. The
+/// index of an instruction in the stream is mapped to its pc in the code with
+/// [`CodeAnalysis::pc`] and back with [`CodeAnalysis::index`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    /// `None` for bytes which are not a valid opcode.
+    op: Option<Opcode>,
+    super_instruction: Option<SuperInstruction>,
+    /// Index of the basic block in [`CodeAnalysis::blocks`] which starts at this instruction or
+    /// [`Instruction::NO_BLOCK`].
+    block: u32,
+    /// The decoded immediate of the instruction:
+    /// - `PUSH1` to `PUSH4`: the pushed value
+    /// - other `PUSH<N>`: the index of the pushed value in [`CodeAnalysis::push_values`]
+    /// - `RJUMP` and `RJUMPI`: the index of the target instruction
+    /// - `RJUMPV`: the index of the jump table in [`CodeAnalysis::jump_tables`]
+    /// - all other EOF opcodes with immediates: the immediate itself
+    arg: u32,
+}
+
+impl Instruction {
+    const STOP: Self = Self {
+        op: Some(Opcode::Stop),
+        super_instruction: None,
+        block: Self::NO_BLOCK,
+        arg: 0,
+    };
+
+    const NO_BLOCK: u32 = u32::MAX;
+
+    /// Longest push value which is stored in [`Instruction::arg`] instead of
+    /// [`CodeAnalysis::push_values`].
+    const INLINE_PUSH_LEN: usize = 4;
+
+    pub fn op(&self) -> Option<Opcode> {
+        self.op
+    }
+
+    pub fn super_instruction(&self) -> Option<SuperInstruction> {
+        self.super_instruction
+    }
+}
+
+/// The result of analyzing a piece of code. It only depends on the code itself and can therefore
+/// be shared between executions of the same code.
+///
+/// For EOF containers only the code sections are analyzed, so all pcs are relative to the start of
+/// the first code section.
+#[derive(Debug)]
+pub struct CodeAnalysis {
+    /// One instruction per opcode followed by an implicit STOP, so that running off the end of the
+    /// code needs no special case.
+    instructions: Box<[Instruction]>,
+    /// The pc of every instruction. The implicit STOP is at the end of the code.
+    pcs: Box<[u32]>,
+    /// The values of pushes which are too long to be stored in the instruction.
+    push_values: Box<[u256]>,
+    /// The jump tables of all `RJUMPV` instructions, each stored as the number of cases followed by
+    /// the index of the target instruction of every case.
+    jump_tables: Box<[u32]>,
+    /// The index of the first instruction of every code section of an EOF container.
+    section_starts: Box<[u32]>,
+    blocks: Box<[BlockInfo]>,
+    /// Only computed on the first jump, because a lot of code (e.g. init code) never jumps.
+    jump_dests: OnceLock<JumpDests>,
+    /// `None` for legacy code.
+    container: Option<Result<Container, EofError>>,
+}
+
+impl CodeAnalysis {
+    /// Analyzes `code` as EOF container if EOF is active in `revision` and the code starts with the
+    /// EOF magic, otherwise as legacy code.
+    pub fn for_revision(code: &[u8], revision: Revision) -> Self {
+        if revision >= Revision::EVMC_OSAKA && Container::is_eof(code) {
+            Self::new_eof(code)
+        } else {
+            Self::new(code)
+        }
+    }
+
+    /// Converts legacy code into an instruction stream, detects super instructions and computes the
+    /// static info of the basic blocks.
+    pub fn new(code: &[u8]) -> Self {
+        Self::analyze(code, None)
+    }
+
+    /// Validates the EOF container `code` and analyzes its code sections. The analysis of an
+    /// invalid container contains no code, executing it fails with the validation error.
+    pub fn new_eof(code: &[u8]) -> Self {
+        match Container::validate(code) {
+            Ok(container) => Self::analyze(&code[container.code_range()], Some(&container))
+                .with_container(Ok(container)),
+            Err(error) => Self::analyze(&[], None).with_container(Err(error)),
+        }
+    }
+
+    fn with_container(mut self, container: Result<Container, EofError>) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Super instructions are only used for legacy code, because in EOF code every instruction
+    /// can be the target of a relative jump and therefore the start of a block.
+    fn analyze(code: &[u8], container: Option<&Container>) -> Self {
+        let mut instructions = Vec::with_capacity(code.len() + 1);
+        let mut pcs = Vec::with_capacity(code.len() + 1);
+        let mut push_values = Vec::new();
+        let mut blocks: Vec<BlockInfo> = Vec::new();
+        let mut in_block = false;
+        let eof = container.is_some();
+        let block_starts = container
+            .map(|container| eof_block_starts(code, container))
+            .unwrap_or_default();
+
+        let mut pc = 0;
+        while pc < code.len() {
+            let (code_byte_type, mut inc) = code_byte_type(code[pc], eof);
+            let mut instruction = Instruction {
+                op: None,
+                super_instruction: None,
+                block: Instruction::NO_BLOCK,
+                arg: 0,
+            };
+            if code_byte_type != CodeByteType::DataOrInvalid {
+                let op = unsafe {
+                    // SAFETY:
+                    // [Opcode] has repr(u8) and therefore the same memory layout as u8.
+                    // code_byte_type determined that this byte is a valid opcode.
+                    // Therefore the value is a valid enum variant.
+                    mem::transmute::<u8, Opcode>(code[pc])
+                };
+                if eof {
+                    inc = eof::instruction_len(op, code, pc);
+                    in_block &= !block_starts[pc];
+                }
+                match StaticInfo::of(op) {
+                    Some(static_info) => {
+                        if !in_block || op == Opcode::JumpDest {
+                            blocks.push(BlockInfo::new());
+                            instruction.block = blocks.len() as u32 - 1;
+                        }
+                        blocks.last_mut().unwrap().add(&static_info);
+                        in_block = !static_info.is_block_end();
+                    }
+                    None => in_block = false,
+                }
+                instruction.op = Some(op);
+                instruction.arg = match push_len(op) {
+                    Some(len) => {
+                        let value = push_value(code, pc, len);
+                        if len <= Instruction::INLINE_PUSH_LEN {
+                            value.into_u64_with_overflow().0 as u32
+                        } else {
+                            push_values.push(value);
+                            push_values.len() as u32 - 1
+                        }
+                    }
+                    // Immediates of EOF instructions are never truncated.
+                    None if inc == 2 => code[pc + 1] as u32,
+                    None if inc == 3 => u16::from_be_bytes([code[pc + 1], code[pc + 2]]) as u32,
+                    None => 0,
+                };
+            } else {
+                in_block = false;
+            }
+            instructions.push(instruction);
+            pcs.push(pc as u32);
+            pc += inc;
+        }
+        instructions.push(Instruction::STOP);
+        pcs.push(code.len() as u32);
+
+        let mut jump_tables = Vec::new();
+        let mut section_starts = Vec::new();
+        if let Some(container) = container {
+            let index = |pc: usize| pcs.binary_search(&(pc as u32)).unwrap() as u32;
+            for (instruction, pc) in instructions.iter_mut().zip(pcs.iter()) {
+                let pc = *pc as usize;
+                let offset =
+                    |index: usize| u16::from_be_bytes([code[pc + index], code[pc + index + 1]]);
+                match instruction.op {
+                    Some(Opcode::RJump | Opcode::RJumpI) => {
+                        instruction.arg = index(eof::relative_target(pc + 3, offset(1)));
+                    }
+                    Some(Opcode::RJumpV) => {
+                        let count = code[pc + 1] as usize + 1;
+                        let next = pc + 2 + 2 * count;
+                        instruction.arg = jump_tables.len() as u32;
+                        jump_tables.push(count as u32);
+                        for case in 0..count {
+                            jump_tables
+                                .push(index(eof::relative_target(next, offset(2 + 2 * case))));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            section_starts = (0..container.code_sections())
+                .map(|section| index(container.code_offset(section)))
+                .collect();
+        } else {
+            for index in 0..instructions.len() {
+                instructions[index].super_instruction =
+                    SuperInstruction::find(&instructions, index);
+            }
+        }
+
+        Self {
+            instructions: instructions.into_boxed_slice(),
+            pcs: pcs.into_boxed_slice(),
+            push_values: push_values.into_boxed_slice(),
+            jump_tables: jump_tables.into_boxed_slice(),
+            section_starts: section_starts.into_boxed_slice(),
+            blocks: blocks.into_boxed_slice(),
+            jump_dests: OnceLock::new(),
+            container: None,
+        }
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Returns the pc of the instruction at `index`, or `None` if `index` is out of range.
+    #[inline(always)]
+    pub fn pc(&self, index: usize) -> Option<usize> {
+        self.pcs.get(index).map(|pc| *pc as usize)
+    }
+
+    /// Returns the index of the instruction at `pc`, or `None` if no instruction starts at `pc`.
+    pub fn index(&self, pc: usize) -> Option<usize> {
+        let pc = u32::try_from(pc).ok()?;
+        self.pcs.binary_search(&pc).ok()
+    }
+
+    /// Returns the basic block which starts at the instruction at `index`, if there is one.
+    #[inline(always)]
+    pub fn block(&self, index: usize) -> Option<&BlockInfo> {
+        let block = self.instructions[index].block;
+        (block != Instruction::NO_BLOCK).then(|| &self.blocks[block as usize])
+    }
+
+    /// Returns the valid jump destinations of the code.
+    pub fn jump_dests(&self) -> &JumpDests {
+        self.jump_dests
+            .get_or_init(|| JumpDests::new(&self.instructions, &self.pcs))
+    }
+
+    /// Returns the value pushed by the `PUSH<len>` instruction at `index`. Missing bytes at the end
+    /// of the code were treated as zeros.
+    #[inline(always)]
+    pub fn push_data(&self, index: usize, len: usize) -> u256 {
+        let arg = self.instructions[index].arg;
+        // len is a constant for the PUSH handlers, so only one of these branches remains.
+        if len <= Instruction::INLINE_PUSH_LEN {
+            (arg as u64).into()
+        } else {
+            self.push_values[arg as usize]
+        }
+    }
+
+    /// Returns the decoded immediate of the EOF instruction at `index`, see [`Instruction::arg`].
+    #[inline(always)]
+    pub fn immediate(&self, index: usize) -> usize {
+        self.instructions[index].arg as usize
+    }
+
+    /// Returns the indices of the target instructions of the `RJUMPV` instruction at `index`.
+    pub fn jump_table(&self, index: usize) -> &[u32] {
+        let start = self.instructions[index].arg as usize;
+        let count = self.jump_tables[start] as usize;
+        &self.jump_tables[start + 1..start + 1 + count]
+    }
+
+    /// Returns the index of the first instruction of code section `section` of an EOF container.
+    pub fn section_start(&self, section: usize) -> usize {
+        self.section_starts[section] as usize
+    }
+
+    /// Returns the validated container of EOF code, `None` for legacy code or the validation error
+    /// of an invalid container.
+    pub fn container(&self) -> Result<Option<&Container>, EofError> {
+        match &self.container {
+            None => Ok(None),
+            Some(Ok(container)) => Ok(Some(container)),
+            Some(Err(error)) => Err(*error),
+        }
+    }
+}
+
+/// Returns `N` for `PUSH<N>` and `None` for all other opcodes.
+pub fn push_len(op: Opcode) -> Option<usize> {
+    let op = op as u8;
+    (Opcode::Push1 as u8..=Opcode::Push32 as u8)
+        .contains(&op)
+        .then(|| (op - Opcode::Push1 as u8 + 1) as usize)
+}
+
+/// Decodes the immediate of the `PUSH<len>` instruction at `pc`. Missing bytes at the end of the
+/// code are treated as zeros.
+fn push_value(code: &[u8], pc: usize, len: usize) -> u256 {
+    let data = &code[pc + 1..min(pc + 1 + len, code.len())];
+    let mut bytes = [0; 32];
+    bytes[32 - len..32 - len + data.len()].copy_from_slice(data);
+    bytes.into()
+}
+
+/// Returns for every pc in the code sections of a validated container whether it is the start of a
+/// code section or the target of a relative jump.
+fn eof_block_starts(code: &[u8], container: &Container) -> Vec<bool> {
+    let mut block_starts = vec![false; code.len()];
+    for section in 0..container.code_sections() {
+        block_starts[container.code_offset(section)] = true;
+    }
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = Opcode::try_from(code[pc]).unwrap();
+        let next = pc + eof::instruction_len(op, code, pc);
+        let offset = |index: usize| u16::from_be_bytes([code[pc + index], code[pc + index + 1]]);
+        match op {
+            Opcode::RJump | Opcode::RJumpI => {
+                block_starts[eof::relative_target(next, offset(1))] = true;
+            }
+            Opcode::RJumpV => {
+                for entry in 0..code[pc + 1] as usize + 1 {
+                    block_starts[eof::relative_target(next, offset(2 + 2 * entry))] = true;
+                }
+            }
+            _ => (),
+        }
+        pc = next;
+    }
+    block_starts
+}
+
+#[cfg(test)]
+mod tests {
+    use evmc_vm::{ExecutionResult, Revision, StatusCode, StepStatusCode};
+
+    use crate::{
+        host::InMemoryHost,
+        interpreter::{
+            blocks::{BlockInfo, StaticInfo},
+            code_state::GetOpcodeError,
+            eof::tests::container,
+            run,
+            tests::message,
+            CodeAnalysis, CodeState, Memory, Stack,
+        },
+        types::{u256, CodeByteType, Opcode},
+    };
+
+    fn code_byte_types(code: &[u8]) -> Vec<CodeByteType> {
+        let analysis = CodeAnalysis::new(code);
+        let mut code_byte_types = vec![CodeByteType::DataOrInvalid; code.len()];
+        for (index, instruction) in analysis.instructions().iter().enumerate() {
+            let pc = analysis.pc(index).unwrap();
+            if pc < code.len() {
+                code_byte_types[pc] = match instruction.op() {
+                    None => CodeByteType::DataOrInvalid,
+                    Some(Opcode::JumpDest) => CodeByteType::JumpDest,
+                    Some(_) => CodeByteType::Opcode,
+                };
+            }
+        }
+        code_byte_types
+    }
+
+    #[test]
+    fn code_byte_types_single_byte() {
+        assert_eq!(
+            *code_byte_types(&[Opcode::Add as u8]),
+            [CodeByteType::Opcode]
+        );
+        assert_eq!(
+            *code_byte_types(&[Opcode::Push2 as u8]),
+            [CodeByteType::Opcode]
+        );
+        assert_eq!(
+            *code_byte_types(&[Opcode::JumpDest as u8]),
+            [CodeByteType::JumpDest]
+        );
+        assert_eq!(*code_byte_types(&[0xc0]), [CodeByteType::DataOrInvalid]);
+    }
+
+    #[test]
+    fn code_byte_types_jumpdest() {
+        assert_eq!(
+            *code_byte_types(&[Opcode::JumpDest as u8, Opcode::Add as u8]),
+            [CodeByteType::JumpDest, CodeByteType::Opcode,]
+        );
+        assert_eq!(
+            *code_byte_types(&[Opcode::JumpDest as u8, 0xc0]),
+            [CodeByteType::JumpDest, CodeByteType::DataOrInvalid,]
+        );
+    }
+
+    #[test]
+    fn code_byte_types_push_with_data() {
+        assert_eq!(
+            *code_byte_types(&[Opcode::Push1 as u8, Opcode::Add as u8, Opcode::Add as u8]),
+            [
+                CodeByteType::Opcode,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::Opcode,
+            ]
+        );
+        assert_eq!(
+            *code_byte_types(&[Opcode::Push1 as u8, Opcode::Add as u8, 0xc0]),
+            [
+                CodeByteType::Opcode,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::DataOrInvalid,
+            ]
+        );
+        assert_eq!(
+            *code_byte_types(&[
+                Opcode::Push1 as u8,
+                Opcode::Add as u8,
+                0xc0,
+                Opcode::Add as u8
+            ]),
+            [
+                CodeByteType::Opcode,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::Opcode,
+            ]
+        );
+        assert_eq!(
+            *code_byte_types(&[
+                Opcode::Push2 as u8,
+                Opcode::Add as u8,
+                Opcode::Add as u8,
+                Opcode::Add as u8,
+            ]),
+            [
+                CodeByteType::Opcode,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::Opcode,
+            ]
+        );
+        assert_eq!(
+            *code_byte_types(&[
+                Opcode::Push2 as u8,
+                Opcode::Add as u8,
+                Opcode::Add as u8,
+                0xc0
+            ]),
+            [
+                CodeByteType::Opcode,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::DataOrInvalid,
+                CodeByteType::DataOrInvalid,
+            ]
+        );
+    }
+
+    #[test]
+    fn push_data_is_decoded() {
+        let mut code = vec![Opcode::Push2 as u8, 0x01, 0x02, Opcode::Push32 as u8];
+        code.extend(1..=32);
+        let analysis = CodeAnalysis::new(&code);
+
+        assert_eq!(analysis.push_data(0, 2), 0x0102u64.into());
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = i as u8 + 1;
+        }
+        assert_eq!(analysis.push_data(1, 32), bytes.into());
+    }
+
+    #[test]
+    fn push_data_at_end_of_code_is_padded_with_zeros() {
+        let analysis = CodeAnalysis::new(&[Opcode::Push2 as u8, 0x01]);
+        assert_eq!(analysis.push_data(0, 2), 0x0100u64.into());
+
+        let analysis = CodeAnalysis::new(&[Opcode::Push8 as u8, 0x01]);
+        assert_eq!(analysis.push_data(0, 8), u256::from(0x01u64 << 56));
+
+        let analysis = CodeAnalysis::new(&[Opcode::Push1 as u8]);
+        assert_eq!(analysis.push_data(0, 1), u256::ZERO);
+    }
+
+    #[test]
+    fn blocks_start_at_jumpdests_and_after_block_ends() {
+        let code = [
+            Opcode::Push1 as u8,
+            0x01,
+            Opcode::Gas as u8,
+            Opcode::Add as u8,
+            Opcode::JumpDest as u8,
+            Opcode::Pop as u8,
+            Opcode::SLoad as u8,
+            Opcode::Pop as u8,
+            0xc0,
+            Opcode::Stop as u8,
+        ];
+        let analysis = CodeAnalysis::new(&code);
+
+        let block = |ops: &[Opcode]| {
+            let mut block = BlockInfo::new();
+            for op in ops {
+                block.add(&StaticInfo::of(*op).unwrap());
+            }
+            block
+        };
+        let blocks: Vec<_> = (0..analysis.instructions().len())
+            .map(|index| analysis.block(index))
+            .collect();
+        assert_eq!(
+            blocks,
+            [
+                Some(&block(&[Opcode::Push1, Opcode::Gas])),
+                None,
+                Some(&block(&[Opcode::Add])),
+                Some(&block(&[Opcode::JumpDest, Opcode::Pop])),
+                None,
+                None,
+                Some(&block(&[Opcode::Pop])),
+                None,
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn pcs_map_between_code_and_instructions() {
+        let code = [Opcode::Push2 as u8, 0x01, 0x02, Opcode::Add as u8];
+        let analysis = CodeAnalysis::new(&code);
+
+        let pcs: Vec<_> = (0..4).map(|index| analysis.pc(index)).collect();
+        assert_eq!(pcs, [Some(0), Some(3), Some(4), None]);
+        let indices: Vec<_> = (0..6).map(|pc| analysis.index(pc)).collect();
+        assert_eq!(indices, [Some(0), None, None, Some(1), Some(2), None]);
+
+        // Stepping can start inside of push data, which is not an instruction.
+        let code_state = CodeState::new(&code, &analysis, 1);
+        assert!(matches!(code_state.get(), Err(GetOpcodeError::Invalid)));
+        assert_eq!(code_state.pc(), 1);
+        let code_state = CodeState::new(&code, &analysis, 5);
+        assert!(matches!(code_state.get(), Err(GetOpcodeError::OutOfRange)));
+        assert_eq!(code_state.pc(), 5);
+    }
+
+    #[test]
+    fn instructions_end_with_stop() {
+        let analysis = CodeAnalysis::new(&[Opcode::Push1 as u8]);
+        assert_eq!(analysis.instructions().len(), 2);
+        assert_eq!(analysis.instructions()[1].op(), Some(Opcode::Stop));
+    }
+
+    #[test]
+    fn jump_dests_are_only_analyzed_by_jumps() {
+        let straight = [
+            Opcode::Push1 as u8,
+            0x01,
+            Opcode::Push0 as u8,
+            Opcode::MStore as u8,
+            Opcode::JumpDest as u8,
+        ];
+        let jumping = [
+            Opcode::Push1 as u8,
+            0x03,
+            Opcode::Jump as u8,
+            Opcode::JumpDest as u8,
+        ];
+        for (code, analyzed) in [(&straight[..], false), (&jumping[..], true)] {
+            let analysis = CodeAnalysis::new(code);
+            let mut host = InMemoryHost::new(Revision::EVMC_CANCUN, Default::default());
+            let result = run(
+                Revision::EVMC_CANCUN,
+                &message(0),
+                &mut host,
+                StepStatusCode::EVMC_STEP_RUNNING,
+                CodeState::new(code, &analysis, 0),
+                0,
+                Stack::new(Vec::new()),
+                Memory::new(Vec::new()),
+                None,
+                None,
+                false,
+                false,
+                None,
+            )
+            .unwrap();
+            let result = ExecutionResult::from(result);
+            assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+            assert_eq!(analysis.jump_dests.get().is_some(), analyzed, "{code:?}");
+        }
+    }
+
+    #[test]
+    fn eof_blocks_start_at_relative_jump_targets() {
+        let code = [
+            Opcode::Push0 as u8,
+            Opcode::RJumpI as u8,
+            0x00,
+            0x01,
+            Opcode::Push0 as u8,
+            Opcode::Push0 as u8,
+            Opcode::Stop as u8,
+        ];
+        let analysis = CodeAnalysis::new_eof(&container(&[(0, 0x80, 2, &code)], &[], &[], 0));
+        assert!(analysis.container().unwrap().is_some());
+
+        let blocks: Vec<_> = (0..analysis.instructions().len())
+            .map(|index| analysis.block(index).is_some())
+            .collect();
+        assert_eq!(blocks, [true, false, true, true, false, false]);
+        // The target of RJUMPI is resolved to the index of the second PUSH0.
+        assert_eq!(analysis.immediate(1), 3);
+        assert_eq!(analysis.pc(3), Some(5));
+    }
+}
@@ -1,14 +1,25 @@
-use std::{cmp::min, mem, ops::Deref};
-
-use evmc_vm::{StatusCode, StepStatusCode};
-
-use crate::types::{code_byte_type, u256, CodeByteType, Opcode};
+use std::ops::Deref;
+
+use crate::{
+    interpreter::{
+        blocks::BlockInfo,
+        eof::{Container, EofError},
+        error::FailureReason,
+        super_instructions::SuperInstruction,
+        CodeAnalysis,
+    },
+    types::{u256, Opcode},
+};
 
 #[derive(Debug)]
 pub struct CodeState<'a> {
     code: &'a [u8],
-    code_byte_type: Box<[CodeByteType]>,
-    pc: usize,
+    analysis: &'a CodeAnalysis,
+    /// The index of the current instruction in [`CodeAnalysis::instructions`].
+    index: usize,
+    /// The pc stepping started at if no instruction starts there. In this case `index` is out of
+    /// range.
+    start_pc: usize,
 }
 
 impl<'a> Deref for CodeState<'a> {
@@ -26,182 +37,107 @@ pub enum GetOpcodeError {
 }
 
 impl<'a> CodeState<'a> {
-    pub fn new(code: &'a [u8], pc: usize) -> Self {
-        let code_byte_type = code_byte_types(code);
+    /// For EOF code, only the code sections of the container are executed, so the pc is relative
+    /// to the first code section.
+    pub fn new(code: &'a [u8], analysis: &'a CodeAnalysis, pc: usize) -> Self {
+        let code = match analysis.container() {
+            Ok(None) => code,
+            Ok(Some(container)) => &code[container.code_range()],
+            Err(_) => &[],
+        };
         Self {
             code,
-            code_byte_type,
-            pc,
+            analysis,
+            index: analysis.index(pc).unwrap_or(usize::MAX),
+            start_pc: pc,
         }
     }
 
+    /// Returns the error if the code is an invalid EOF container.
+    pub fn container_validation(&self) -> Result<(), EofError> {
+        self.analysis.container().map(|_| ())
+    }
+
+    /// Returns the container of EOF code or `None` for legacy code.
+    pub fn container(&self) -> Option<&'a Container> {
+        self.analysis.container().ok().flatten()
+    }
+
     pub fn get(&self) -> Result<Opcode, GetOpcodeError> {
-        if self.pc >= self.code.len() {
-            Err(GetOpcodeError::OutOfRange)
-        } else if self.code_byte_type[self.pc] == CodeByteType::DataOrInvalid {
-            Err(GetOpcodeError::Invalid)
-        } else {
-            let op = self.code[self.pc];
-            let op = unsafe {
-                // SAFETY:
-                // [Opcode] has repr(u8) and therefore the same memory layout as u8.
-                // In get_code_byte_types this byte of the code was determined to be a valid opcode.
-                // Therefore the value is a valid enum variant.
-                mem::transmute::<u8, Opcode>(op)
-            };
-            Ok(op)
+        match self.analysis.instructions().get(self.index) {
+            Some(instruction) => instruction.op().ok_or(GetOpcodeError::Invalid),
+            // Stepping started inside of the immediate of an instruction.
+            None if self.start_pc < self.code.len() => Err(GetOpcodeError::Invalid),
+            None => Err(GetOpcodeError::OutOfRange),
         }
     }
 
+    /// Returns the basic block starting at the current instruction, if there is one.
+    pub fn get_block(&self) -> Option<&BlockInfo> {
+        self.analysis.block(self.index)
+    }
+
+    /// Returns the super instruction starting at the current instruction, if there is one.
+    pub fn get_super_instruction(&self) -> Option<SuperInstruction> {
+        self.analysis.instructions()[self.index].super_instruction()
+    }
+
     pub fn next(&mut self) {
-        self.pc += 1;
-    }
-
-    pub fn try_jump(&mut self, dest: u256) -> Result<(), (StepStatusCode, StatusCode)> {
-        let (dest, dest_overflow) = dest.into_u64_with_overflow();
-        let dest = dest as usize;
-        if dest_overflow
-            || dest >= self.code_byte_type.len()
-            || self.code_byte_type[dest] != CodeByteType::JumpDest
-        {
-            return Err((
-                StepStatusCode::EVMC_STEP_FAILED,
-                StatusCode::EVMC_BAD_JUMP_DESTINATION,
-            ));
+        self.index += 1;
+    }
+
+    pub fn try_jump(&mut self, dest: u256) -> Result<(), FailureReason> {
+        let (pc, pc_overflow) = dest.into_u64_with_overflow();
+        if pc_overflow || !self.analysis.jump_dests().contains(pc as usize) {
+            return Err(FailureReason::BadJumpDestination(dest));
         }
-        self.pc = dest;
+        // Every valid jump destination is the pc of an instruction.
+        self.index = self.analysis.index(pc as usize).unwrap();
 
         Ok(())
     }
 
-    pub fn get_push_data(&mut self, len: usize) -> Result<u256, (StepStatusCode, StatusCode)> {
-        assert!(len <= 32);
+    /// Returns the immediate of the `PUSH<len>` instruction at the current pc and moves to the
+    /// next instruction.
+    pub fn get_push_data(&mut self, len: usize) -> u256 {
+        let data = self.analysis.push_data(self.index, len);
+        self.index += 1;
 
-        let len = min(len, self.code.len() - self.pc);
-        let mut bytes = [0; 32];
-        bytes[32 - len..].copy_from_slice(&self.code[self.pc..self.pc + len]);
-        self.pc += len;
+        data
+    }
 
-        Ok(bytes.into())
+    /// Returns the decoded immediate of the current EOF instruction.
+    #[inline(always)]
+    pub fn immediate(&self) -> usize {
+        self.analysis.immediate(self.index)
     }
 
-    pub fn pc(&self) -> usize {
-        self.pc
+    /// Returns the indices of the target instructions of the current `RJUMPV` instruction.
+    pub fn jump_table(&self) -> &'a [u32] {
+        self.analysis.jump_table(self.index)
     }
 
-    pub fn code_len(&self) -> usize {
-        self.code.len()
+    /// Continues at the instruction at `index` without checks. Only used for EOF code, where all
+    /// jump targets were validated.
+    pub fn jump_to(&mut self, index: usize) {
+        self.index = index;
     }
-}
 
-fn code_byte_types(code: &[u8]) -> Box<[CodeByteType]> {
-    let mut jump_destinations = vec![CodeByteType::DataOrInvalid; code.len()];
+    /// Continues at the first instruction of code section `section` of an EOF container.
+    pub fn jump_to_section(&mut self, section: usize) {
+        self.index = self.analysis.section_start(section);
+    }
 
-    let mut pc = 0;
-    while pc < code.len() {
-        let (code_byte_type, inc) = code_byte_type(code[pc]);
-        jump_destinations[pc] = code_byte_type;
-        pc += inc;
+    /// The index of the current instruction in the instruction stream.
+    pub fn index(&self) -> usize {
+        self.index
     }
 
-    jump_destinations.into_boxed_slice()
-}
+    pub fn pc(&self) -> usize {
+        self.analysis.pc(self.index).unwrap_or(self.start_pc)
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::{
-        interpreter::code_state::code_byte_types,
-        types::{CodeByteType, Opcode},
-    };
-
-    #[test]
-    fn code_byte_types_single_byte() {
-        assert_eq!(
-            *code_byte_types(&[Opcode::Add as u8]),
-            [CodeByteType::Opcode]
-        );
-        assert_eq!(
-            *code_byte_types(&[Opcode::Push2 as u8]),
-            [CodeByteType::Opcode]
-        );
-        assert_eq!(
-            *code_byte_types(&[Opcode::JumpDest as u8]),
-            [CodeByteType::JumpDest]
-        );
-        assert_eq!(*code_byte_types(&[0xc0]), [CodeByteType::DataOrInvalid]);
-    }
-
-    #[test]
-    fn code_byte_types_jumpdest() {
-        assert_eq!(
-            *code_byte_types(&[Opcode::JumpDest as u8, Opcode::Add as u8]),
-            [CodeByteType::JumpDest, CodeByteType::Opcode,]
-        );
-        assert_eq!(
-            *code_byte_types(&[Opcode::JumpDest as u8, 0xc0]),
-            [CodeByteType::JumpDest, CodeByteType::DataOrInvalid,]
-        );
-    }
-
-    #[test]
-    fn code_byte_types_push_with_data() {
-        assert_eq!(
-            *code_byte_types(&[Opcode::Push1 as u8, Opcode::Add as u8, Opcode::Add as u8]),
-            [
-                CodeByteType::Opcode,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::Opcode,
-            ]
-        );
-        assert_eq!(
-            *code_byte_types(&[Opcode::Push1 as u8, Opcode::Add as u8, 0xc0]),
-            [
-                CodeByteType::Opcode,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::DataOrInvalid,
-            ]
-        );
-        assert_eq!(
-            *code_byte_types(&[
-                Opcode::Push1 as u8,
-                Opcode::Add as u8,
-                0xc0,
-                Opcode::Add as u8
-            ]),
-            [
-                CodeByteType::Opcode,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::Opcode,
-            ]
-        );
-        assert_eq!(
-            *code_byte_types(&[
-                Opcode::Push2 as u8,
-                Opcode::Add as u8,
-                Opcode::Add as u8,
-                Opcode::Add as u8,
-            ]),
-            [
-                CodeByteType::Opcode,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::Opcode,
-            ]
-        );
-        assert_eq!(
-            *code_byte_types(&[
-                Opcode::Push2 as u8,
-                Opcode::Add as u8,
-                Opcode::Add as u8,
-                0xc0
-            ]),
-            [
-                CodeByteType::Opcode,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::DataOrInvalid,
-                CodeByteType::DataOrInvalid,
-            ]
-        );
+    pub fn code_len(&self) -> usize {
+        self.code.len()
     }
 }
@@ -0,0 +1,871 @@
+use std::{collections::VecDeque, fmt, ops::Range};
+
+use crate::{
+    interpreter::stack::STACK_SIZE_LIMIT,
+    types::{code_byte_type, CodeByteType, Opcode},
+};
+
+const MAGIC: [u8; 2] = [0xef, 0x00];
+const VERSION: u8 = 0x01;
+
+const KIND_TYPES: u8 = 0x01;
+const KIND_CODE: u8 = 0x02;
+const KIND_CONTAINER: u8 = 0x03;
+const KIND_DATA: u8 = 0xff;
+const TERMINATOR: u8 = 0x00;
+
+const MAX_CODE_SECTIONS: usize = 1024;
+const MAX_CONTAINER_SECTIONS: usize = 256;
+const MAX_INPUTS_OUTPUTS: u8 = 0x7f;
+const MAX_STACK_INCREASE: u16 = 0x03ff;
+const NON_RETURNING: u8 = 0x80;
+
+/// Why an EOF container failed validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EofError {
+    /// Wrong magic or version, unexpected section kinds or invalid section counts or sizes.
+    InvalidHeader,
+    /// The body is shorter or longer than the sizes in the header.
+    InvalidBodySize,
+    /// The data section is shorter than its declared size in a container which is not deployed
+    /// with aux data by RETURNCONTRACT.
+    TruncatedData,
+    /// The inputs, outputs or max stack increase of a code section are out of range.
+    InvalidTypes,
+    UndefinedInstruction,
+    TruncatedImmediate,
+    /// A relative jump targets an immediate or a position outside of its code section.
+    InvalidRelativeJump,
+    InvalidCodeSectionIndex,
+    CallfToNonReturning,
+    /// A code section is declared as non-returning but returns or vice versa.
+    InvalidNonReturningFlag,
+    InvalidDataLoadN,
+    InvalidContainerIndex,
+    UnreferencedSubcontainer,
+    /// A subcontainer is used both as init code and as runtime code or contains an opcode which
+    /// is not allowed for its kind.
+    IncompatibleContainerKind,
+    StackUnderflow,
+    StackOverflow,
+    /// The stack height differs between paths which meet, at a RETF or at a JUMPF to a returning
+    /// code section.
+    StackHeightMismatch,
+    InvalidMaxStackIncrease,
+    UnreachableCode,
+    UnreachableCodeSection,
+    /// The execution can run past the end of a code section.
+    NoTerminatingInstruction,
+    /// A JUMPF targets a returning code section with more outputs than the current one.
+    InvalidJumpfTarget,
+}
+
+impl fmt::Display for EofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::InvalidHeader => "invalid header",
+            Self::InvalidBodySize => "body size does not match header",
+            Self::TruncatedData => "truncated data section",
+            Self::InvalidTypes => "invalid types section",
+            Self::UndefinedInstruction => "undefined instruction",
+            Self::TruncatedImmediate => "truncated immediate",
+            Self::InvalidRelativeJump => "invalid relative jump",
+            Self::InvalidCodeSectionIndex => "invalid code section index",
+            Self::CallfToNonReturning => "CALLF to non-returning code section",
+            Self::InvalidNonReturningFlag => "invalid non-returning flag",
+            Self::InvalidDataLoadN => "DATALOADN out of bounds",
+            Self::InvalidContainerIndex => "invalid container index",
+            Self::UnreferencedSubcontainer => "unreferenced subcontainer",
+            Self::IncompatibleContainerKind => "incompatible container kind",
+            Self::StackUnderflow => "stack underflow",
+            Self::StackOverflow => "stack overflow",
+            Self::StackHeightMismatch => "stack height mismatch",
+            Self::InvalidMaxStackIncrease => "invalid max stack increase",
+            Self::UnreachableCode => "unreachable code",
+            Self::UnreachableCodeSection => "unreachable code section",
+            Self::NoTerminatingInstruction => "no terminating instruction",
+            Self::InvalidJumpfTarget => "invalid JUMPF target",
+        };
+        f.write_str(message)
+    }
+}
+
+/// How a subcontainer is used, which determines the opcodes it may contain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContainerKind {
+    /// Referenced by EOFCREATE. Must not contain RETURN or STOP.
+    Initcode,
+    /// Referenced by RETURNCONTRACT. Must not contain RETURNCONTRACT and may have a truncated data
+    /// section, which is filled with the aux data on deployment.
+    Runtime,
+}
+
+/// The entry of a code section in the types section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeType {
+    pub inputs: u8,
+    /// [`NON_RETURNING`] for code sections which never return to the caller.
+    pub outputs: u8,
+    pub max_stack_increase: u16,
+}
+
+impl CodeType {
+    pub fn is_returning(&self) -> bool {
+        self.outputs != NON_RETURNING
+    }
+}
+
+/// The sizes in the header of a container.
+struct Header {
+    code_sizes: Vec<usize>,
+    container_sizes: Vec<usize>,
+    data_size: usize,
+    /// The length of the header, which is the offset of the types section.
+    len: usize,
+}
+
+impl Header {
+    fn parse(code: &[u8]) -> Result<Self, EofError> {
+        let mut pos = 0;
+        let mut read = |len: usize| {
+            let bytes = code.get(pos..pos + len).ok_or(EofError::InvalidHeader)?;
+            pos += len;
+            Ok(bytes.iter().fold(0, |acc, byte| acc << 8 | *byte as usize))
+        };
+        let magic_and_version = read(3)?;
+        let types_kind = read(1)?;
+        let types_size = read(2)?;
+        let code_kind = read(1)?;
+        let code_sections = read(2)?;
+        if magic_and_version
+            != (MAGIC[0] as usize) << 16 | (MAGIC[1] as usize) << 8 | VERSION as usize
+            || types_kind != KIND_TYPES as usize
+            || code_kind != KIND_CODE as usize
+            || code_sections == 0
+            || code_sections > MAX_CODE_SECTIONS
+            || types_size != 4 * code_sections
+        {
+            return Err(EofError::InvalidHeader);
+        }
+        let code_sizes = (0..code_sections)
+            .map(|_| read(2))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut kind = read(1)?;
+        let mut container_sizes = Vec::new();
+        if kind == KIND_CONTAINER as usize {
+            let container_sections = read(2)?;
+            if container_sections == 0 || container_sections > MAX_CONTAINER_SECTIONS {
+                return Err(EofError::InvalidHeader);
+            }
+            container_sizes = (0..container_sections)
+                .map(|_| read(4))
+                .collect::<Result<Vec<_>, _>>()?;
+            kind = read(1)?;
+        }
+        if kind != KIND_DATA as usize {
+            return Err(EofError::InvalidHeader);
+        }
+        let data_size = read(2)?;
+        if read(1)? != TERMINATOR as usize
+            || code_sizes.contains(&0)
+            || container_sizes.contains(&0)
+        {
+            return Err(EofError::InvalidHeader);
+        }
+
+        Ok(Self {
+            code_sizes,
+            container_sizes,
+            data_size,
+            len: pos,
+        })
+    }
+
+    /// The offset of the data size in the header.
+    fn data_size_offset(&self) -> usize {
+        self.len - 3 // data size and terminator
+    }
+
+    fn data_offset(&self) -> usize {
+        self.len
+            + 4 * self.code_sizes.len()
+            + self.code_sizes.iter().sum::<usize>()
+            + self.container_sizes.iter().sum::<usize>()
+    }
+}
+
+/// A validated EOF v1 container (EIP-3540).
+///
+/// The code sections are contiguous, so they are executed as one piece of code in which the pc is
+/// relative to the start of the first code section.
+#[derive(Debug)]
+pub struct Container {
+    types: Box<[CodeType]>,
+    /// The offsets of the code sections relative to the first code section.
+    code_offsets: Box<[usize]>,
+    /// The range of all code sections in the container.
+    code: Range<usize>,
+    containers: Box<[Box<[u8]>]>,
+    data: Box<[u8]>,
+}
+
+impl Container {
+    /// Whether `code` starts with the EOF magic and therefore has to be a valid container if EOF
+    /// is active.
+    pub fn is_eof(code: &[u8]) -> bool {
+        code.starts_with(&MAGIC)
+    }
+
+    /// Validates a top level container. Its kind is not known, so opcodes for both init code and
+    /// runtime code are allowed. Subcontainers are validated for the kind they are used as.
+    pub fn validate(code: &[u8]) -> Result<Self, EofError> {
+        Self::validate_with_kind(code, None)
+    }
+
+    fn validate_with_kind(code: &[u8], kind: Option<ContainerKind>) -> Result<Self, EofError> {
+        let header = Header::parse(code)?;
+        let data_offset = header.data_offset();
+        if code.len() < data_offset || code.len() > data_offset + header.data_size {
+            return Err(EofError::InvalidBodySize);
+        }
+        if code.len() < data_offset + header.data_size && kind != Some(ContainerKind::Runtime) {
+            return Err(EofError::TruncatedData);
+        }
+
+        let types: Box<[_]> = code[header.len..header.len + 4 * header.code_sizes.len()]
+            .chunks_exact(4)
+            .map(|entry| CodeType {
+                inputs: entry[0],
+                outputs: entry[1],
+                max_stack_increase: u16::from_be_bytes([entry[2], entry[3]]),
+            })
+            .collect();
+        if types[0].inputs != 0 || types[0].is_returning() {
+            return Err(EofError::InvalidTypes);
+        }
+        if types.iter().any(|ty| {
+            ty.inputs > MAX_INPUTS_OUTPUTS
+                || (ty.outputs > MAX_INPUTS_OUTPUTS && ty.is_returning())
+                || ty.max_stack_increase > MAX_STACK_INCREASE
+        }) {
+            return Err(EofError::InvalidTypes);
+        }
+
+        let code_start = header.len + types.len() * 4;
+        let mut code_offsets = Vec::with_capacity(header.code_sizes.len());
+        let mut offset = 0;
+        for size in &header.code_sizes {
+            code_offsets.push(offset);
+            offset += size;
+        }
+        let code_end = code_start + offset;
+
+        let mut containers = Vec::with_capacity(header.container_sizes.len());
+        let mut offset = code_end;
+        for size in &header.container_sizes {
+            containers.push(Box::from(&code[offset..offset + size]));
+            offset += size;
+        }
+
+        let container = Self {
+            types,
+            code_offsets: code_offsets.into_boxed_slice(),
+            code: code_start..code_end,
+            containers: containers.into_boxed_slice(),
+            data: Box::from(&code[data_offset..]),
+        };
+        container.validate_code(&code[code_start..code_end], header.data_size, kind)?;
+        Ok(container)
+    }
+
+    /// Validates the code sections (EIP-3670, EIP-4750, EIP-5450) and the subcontainers.
+    fn validate_code(
+        &self,
+        code: &[u8],
+        data_size: usize,
+        kind: Option<ContainerKind>,
+    ) -> Result<(), EofError> {
+        let mut references = Vec::with_capacity(self.types.len());
+        let mut container_kinds = vec![None; self.containers.len()];
+        for section in 0..self.types.len() {
+            let section_code = &code[self.section_range(section, code.len())];
+            let instructions = decode(section_code)?;
+            let mut referenced_sections = Vec::new();
+            let mut returns = false;
+            for (pos, op) in instructions.iter().copied().enumerate() {
+                let Some(op) = op else {
+                    continue;
+                };
+                let immediate = |index: usize| section_code[pos + 1 + index];
+                let immediate_u16 =
+                    |index| u16::from_be_bytes([immediate(index), immediate(index + 1)]);
+                match op {
+                    Opcode::CallF | Opcode::JumpF => {
+                        let target = immediate_u16(0) as usize;
+                        let target_type = self
+                            .types
+                            .get(target)
+                            .ok_or(EofError::InvalidCodeSectionIndex)?;
+                        if op == Opcode::CallF && !target_type.is_returning() {
+                            return Err(EofError::CallfToNonReturning);
+                        }
+                        returns |= op == Opcode::JumpF && target_type.is_returning();
+                        referenced_sections.push(target);
+                    }
+                    Opcode::RetF => returns = true,
+                    Opcode::DataLoadN if immediate_u16(0) as usize + 32 > data_size => {
+                        return Err(EofError::InvalidDataLoadN);
+                    }
+                    Opcode::EofCreate | Opcode::ReturnContract => {
+                        let container_kind = if op == Opcode::EofCreate {
+                            ContainerKind::Initcode
+                        } else {
+                            ContainerKind::Runtime
+                        };
+                        let previous = container_kinds
+                            .get_mut(immediate(0) as usize)
+                            .ok_or(EofError::InvalidContainerIndex)?;
+                        if previous.is_some_and(|previous| previous != container_kind) {
+                            return Err(EofError::IncompatibleContainerKind);
+                        }
+                        *previous = Some(container_kind);
+                    }
+                    _ => (),
+                }
+                let forbidden = match kind {
+                    Some(ContainerKind::Initcode) => {
+                        matches!(op, Opcode::Return | Opcode::Stop)
+                    }
+                    Some(ContainerKind::Runtime) => op == Opcode::ReturnContract,
+                    None => false,
+                };
+                if forbidden {
+                    return Err(EofError::IncompatibleContainerKind);
+                }
+            }
+            if returns != self.types[section].is_returning() {
+                return Err(EofError::InvalidNonReturningFlag);
+            }
+            self.validate_stack(section, section_code, &instructions)?;
+            references.push(referenced_sections);
+        }
+
+        let mut reachable = vec![false; self.types.len()];
+        reachable[0] = true;
+        let mut queue = VecDeque::from([0]);
+        while let Some(section) = queue.pop_front() {
+            for &target in &references[section] {
+                if !reachable[target] {
+                    reachable[target] = true;
+                    queue.push_back(target);
+                }
+            }
+        }
+        if reachable.contains(&false) {
+            return Err(EofError::UnreachableCodeSection);
+        }
+
+        for (container, kind) in self.containers.iter().zip(container_kinds) {
+            let kind = kind.ok_or(EofError::UnreferencedSubcontainer)?;
+            Self::validate_with_kind(container, Some(kind))?;
+        }
+        Ok(())
+    }
+
+    /// Computes the range of stack heights of each instruction in a single pass and checks that
+    /// the stack never underflows, that paths which meet through a backwards jump have the same
+    /// height and that the declared max stack increase is exact (EIP-5450).
+    fn validate_stack(
+        &self,
+        section: usize,
+        code: &[u8],
+        instructions: &[Option<Opcode>],
+    ) -> Result<(), EofError> {
+        let code_type = self.types[section];
+        let inputs = code_type.inputs as usize;
+        let mut heights = vec![None; code.len()];
+        heights[0] = Some((inputs, inputs));
+        let mut max_height = inputs;
+
+        for (pos, op) in instructions.iter().copied().enumerate() {
+            let Some(op) = op else {
+                continue;
+            };
+            let (min, max) = heights[pos].ok_or(EofError::UnreachableCode)?;
+            let immediate = |index: usize| code[pos + 1 + index];
+            let immediate_u16 =
+                |index| u16::from_be_bytes([immediate(index), immediate(index + 1)]);
+            let len = instruction_len(op, code, pos);
+
+            let (required, pushed) = match op {
+                Opcode::CallF | Opcode::JumpF => {
+                    let target = self.types[immediate_u16(0) as usize];
+                    if max + target.max_stack_increase as usize > STACK_SIZE_LIMIT {
+                        return Err(EofError::StackOverflow);
+                    }
+                    if op == Opcode::JumpF && target.is_returning() {
+                        if target.outputs > code_type.outputs {
+                            return Err(EofError::InvalidJumpfTarget);
+                        }
+                        let height = code_type.outputs as usize + target.inputs as usize
+                            - target.outputs as usize;
+                        if min != height || max != height {
+                            return Err(EofError::StackHeightMismatch);
+                        }
+                    }
+                    let outputs = if target.is_returning() {
+                        target.outputs
+                    } else {
+                        0
+                    };
+                    (target.inputs as usize, outputs as usize)
+                }
+                Opcode::RetF => {
+                    let outputs = code_type.outputs as usize;
+                    if min != outputs || max != outputs {
+                        return Err(EofError::StackHeightMismatch);
+                    }
+                    (outputs, 0)
+                }
+                Opcode::DupN => {
+                    let n = immediate(0) as usize + 1;
+                    (n, n + 1)
+                }
+                Opcode::SwapN => {
+                    let n = immediate(0) as usize + 2;
+                    (n, n)
+                }
+                Opcode::Exchange => {
+                    let n = (immediate(0) >> 4) as usize + (immediate(0) & 0x0f) as usize + 3;
+                    (n, n)
+                }
+                _ => stack_effect(op),
+            };
+            if min < required {
+                return Err(EofError::StackUnderflow);
+            }
+            let next = (min - required + pushed, max - required + pushed);
+            max_height = max_height.max(next.1);
+
+            let mut successors = Vec::new();
+            if !is_terminating(op) && op != Opcode::RJump {
+                successors.push(pos + len);
+            }
+            match op {
+                Opcode::RJump | Opcode::RJumpI => {
+                    successors.push(relative_target(pos + len, immediate_u16(0)));
+                }
+                Opcode::RJumpV => {
+                    for entry in 0..immediate(0) as usize + 1 {
+                        successors.push(relative_target(pos + len, immediate_u16(1 + 2 * entry)));
+                    }
+                }
+                _ => (),
+            }
+            for successor in successors {
+                if successor >= code.len() {
+                    return Err(if successor == pos + len {
+                        EofError::NoTerminatingInstruction
+                    } else {
+                        EofError::InvalidRelativeJump
+                    });
+                }
+                if instructions[successor].is_none() {
+                    return Err(EofError::InvalidRelativeJump);
+                }
+                if successor > pos {
+                    heights[successor] = Some(match heights[successor] {
+                        Some((min, max)) => (min.min(next.0), max.max(next.1)),
+                        None => next,
+                    });
+                } else if heights[successor] != Some(next) {
+                    return Err(EofError::StackHeightMismatch);
+                }
+            }
+        }
+
+        if max_height > STACK_SIZE_LIMIT {
+            return Err(EofError::StackOverflow);
+        }
+        if max_height != inputs + code_type.max_stack_increase as usize {
+            return Err(EofError::InvalidMaxStackIncrease);
+        }
+        Ok(())
+    }
+
+    fn section_range(&self, section: usize, code_len: usize) -> Range<usize> {
+        let end = self
+            .code_offsets
+            .get(section + 1)
+            .copied()
+            .unwrap_or(code_len);
+        self.code_offsets[section]..end
+    }
+
+    /// The range of all code sections in the container.
+    pub fn code_range(&self) -> Range<usize> {
+        self.code.clone()
+    }
+
+    pub fn code_sections(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn code_type(&self, section: usize) -> CodeType {
+        self.types[section]
+    }
+
+    /// The offset of a code section relative to the first code section.
+    pub fn code_offset(&self, section: usize) -> usize {
+        self.code_offsets[section]
+    }
+
+    pub fn container(&self, index: usize) -> &[u8] {
+        &self.containers[index]
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Appends `aux_data` to the data section of the validated runtime container `container` and
+/// updates the data size in its header, as done by RETURNCONTRACT (EIP-7620). Fails if the data
+/// section is still truncated afterwards or if its size does not fit into the header.
+pub fn append_aux_data(container: &[u8], aux_data: &[u8]) -> Result<Vec<u8>, EofError> {
+    let header = Header::parse(container)?;
+    let data_size = container.len() - header.data_offset() + aux_data.len();
+    if data_size < header.data_size {
+        return Err(EofError::TruncatedData);
+    }
+    if data_size > u16::MAX as usize {
+        return Err(EofError::InvalidHeader);
+    }
+    let mut deployed = [container, aux_data].concat();
+    let offset = header.data_size_offset();
+    deployed[offset..offset + 2].copy_from_slice(&(data_size as u16).to_be_bytes());
+    Ok(deployed)
+}
+
+/// Returns the opcode at every instruction start of a code section and `None` for immediates.
+fn decode(code: &[u8]) -> Result<Vec<Option<Opcode>>, EofError> {
+    let mut instructions = vec![None; code.len()];
+    let mut pos = 0;
+    while pos < code.len() {
+        let Ok(op) = Opcode::try_from(code[pos]) else {
+            return Err(EofError::UndefinedInstruction);
+        };
+        if code_byte_type(code[pos], true).0 == CodeByteType::DataOrInvalid {
+            return Err(EofError::UndefinedInstruction);
+        }
+        if op == Opcode::RJumpV && pos + 1 >= code.len() {
+            return Err(EofError::TruncatedImmediate);
+        }
+        let len = instruction_len(op, code, pos);
+        if pos + len > code.len() {
+            return Err(EofError::TruncatedImmediate);
+        }
+        instructions[pos] = Some(op);
+        pos += len;
+    }
+    Ok(instructions)
+}
+
+/// The length of the instruction at `pos` including its immediates. The count of the jump table of
+/// `RJUMPV` must be in bounds.
+pub fn instruction_len(op: Opcode, code: &[u8], pos: usize) -> usize {
+    match op {
+        Opcode::RJumpV => 2 + 2 * (code[pos + 1] as usize + 1),
+        _ => code_byte_type(op as u8, true).1,
+    }
+}
+
+/// Returns the target of a relative jump whose next instruction is at `next`.
+pub fn relative_target(next: usize, offset: u16) -> usize {
+    next.wrapping_add_signed(offset as i16 as isize)
+}
+
+fn is_terminating(op: Opcode) -> bool {
+    matches!(
+        op,
+        Opcode::Stop
+            | Opcode::Return
+            | Opcode::Revert
+            | Opcode::Invalid
+            | Opcode::RetF
+            | Opcode::JumpF
+            | Opcode::ReturnContract
+    )
+}
+
+/// The number of stack elements an opcode with a fixed stack effect requires and the number of
+/// elements it pushes after popping them.
+fn stack_effect(op: Opcode) -> (usize, usize) {
+    let op = op as u8;
+    match op {
+        _ if (Opcode::Push0 as u8..=Opcode::Push32 as u8).contains(&op) => (0, 1),
+        _ if (Opcode::Dup1 as u8..=Opcode::Dup16 as u8).contains(&op) => {
+            let n = (op - Opcode::Dup1 as u8) as usize + 1;
+            (n, n + 1)
+        }
+        _ if (Opcode::Swap1 as u8..=Opcode::Swap16 as u8).contains(&op) => {
+            let n = (op - Opcode::Swap1 as u8) as usize + 2;
+            (n, n)
+        }
+        _ if (Opcode::Log0 as u8..=Opcode::Log4 as u8).contains(&op) => {
+            ((op - Opcode::Log0 as u8) as usize + 2, 0)
+        }
+        _ => match Opcode::try_from(op).unwrap() {
+            Opcode::Stop | Opcode::JumpDest | Opcode::Invalid | Opcode::RJump => (0, 0),
+            Opcode::Address
+            | Opcode::Origin
+            | Opcode::Caller
+            | Opcode::CallValue
+            | Opcode::CallDataSize
+            | Opcode::GasPrice
+            | Opcode::ReturnDataSize
+            | Opcode::Coinbase
+            | Opcode::Timestamp
+            | Opcode::Number
+            | Opcode::PrevRandao
+            | Opcode::GasLimit
+            | Opcode::ChainId
+            | Opcode::SelfBalance
+            | Opcode::BaseFee
+            | Opcode::BlobBaseFee
+            | Opcode::MSize
+            | Opcode::DataLoadN
+            | Opcode::DataSize => (0, 1),
+            Opcode::IsZero
+            | Opcode::Not
+            | Opcode::Balance
+            | Opcode::CallDataLoad
+            | Opcode::BlockHash
+            | Opcode::BlobHash
+            | Opcode::MLoad
+            | Opcode::SLoad
+            | Opcode::TLoad
+            | Opcode::DataLoad
+            | Opcode::ReturnDataLoad => (1, 1),
+            Opcode::Pop | Opcode::RJumpI | Opcode::RJumpV => (1, 0),
+            Opcode::MStore
+            | Opcode::MStore8
+            | Opcode::SStore
+            | Opcode::TStore
+            | Opcode::Return
+            | Opcode::Revert
+            | Opcode::ReturnContract => (2, 0),
+            Opcode::CallDataCopy | Opcode::ReturnDataCopy | Opcode::MCopy | Opcode::DataCopy => {
+                (3, 0)
+            }
+            Opcode::AddMod | Opcode::MulMod | Opcode::ExtDelegateCall | Opcode::ExtStaticCall => {
+                (3, 1)
+            }
+            Opcode::ExtCall | Opcode::EofCreate => (4, 1),
+            // Binary operations. The remaining opcodes are handled by the caller or are not
+            // allowed in EOF code.
+            _ => (2, 1),
+        },
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{
+        interpreter::eof::{append_aux_data, Container, EofError},
+        types::Opcode,
+    };
+
+    /// A code section as `(inputs, outputs, max stack increase, code)`.
+    pub(crate) type Section<'a> = (u8, u8, u16, &'a [u8]);
+
+    /// Returns a container with the given code sections, subcontainers and data.
+    pub(crate) fn container(
+        sections: &[Section],
+        containers: &[&[u8]],
+        data: &[u8],
+        data_size: u16,
+    ) -> Vec<u8> {
+        let mut header = vec![0xef, 0x00, 0x01, 0x01];
+        header.extend((4 * sections.len() as u16).to_be_bytes());
+        header.push(0x02);
+        header.extend((sections.len() as u16).to_be_bytes());
+        for (_, _, _, code) in sections {
+            header.extend((code.len() as u16).to_be_bytes());
+        }
+        if !containers.is_empty() {
+            header.push(0x03);
+            header.extend((containers.len() as u16).to_be_bytes());
+            for container in containers {
+                header.extend((container.len() as u32).to_be_bytes());
+            }
+        }
+        header.push(0xff);
+        header.extend(data_size.to_be_bytes());
+        header.push(0x00);
+        for (inputs, outputs, max_stack_increase, _) in sections {
+            header.extend([*inputs, *outputs]);
+            header.extend(max_stack_increase.to_be_bytes());
+        }
+        for (_, _, _, code) in sections {
+            header.extend_from_slice(code);
+        }
+        for container in containers {
+            header.extend_from_slice(container);
+        }
+        header.extend_from_slice(data);
+        header
+    }
+
+    fn validate(sections: &[Section]) -> Result<(), EofError> {
+        Container::validate(&container(sections, &[], &[], 0)).map(|_| ())
+    }
+
+    const STOP: u8 = Opcode::Stop as u8;
+    const PUSH0: u8 = Opcode::Push0 as u8;
+    const POP: u8 = Opcode::Pop as u8;
+    const RJUMP: u8 = Opcode::RJump as u8;
+    const RJUMPI: u8 = Opcode::RJumpI as u8;
+    const CALLF: u8 = Opcode::CallF as u8;
+    const RETF: u8 = Opcode::RetF as u8;
+
+    #[test]
+    fn container_layout_is_parsed() {
+        let code = container(
+            &[
+                (0, 0x80, 1, &[PUSH0, CALLF, 0, 1, STOP]),
+                (1, 1, 0, &[RETF]),
+            ],
+            &[],
+            &[1, 2, 3],
+            3,
+        );
+        let container = Container::validate(&code).unwrap();
+        assert_eq!(
+            code[container.code_range()],
+            [PUSH0, CALLF, 0, 1, STOP, RETF]
+        );
+        assert_eq!(container.code_offset(1), 5);
+        assert_eq!(container.code_type(1).inputs, 1);
+        assert!(container.code_type(1).is_returning());
+        assert_eq!(container.data(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let valid = container(&[(0, 0x80, 0, &[STOP])], &[], &[], 0);
+        assert!(Container::validate(&valid).is_ok());
+
+        let mut wrong_version = valid.clone();
+        wrong_version[2] = 0x02;
+        let truncated = &valid[..10];
+        let mut trailing = valid.clone();
+        trailing.push(0);
+        let cases = [
+            (&wrong_version[..], EofError::InvalidHeader),
+            (truncated, EofError::InvalidHeader),
+            (&trailing, EofError::InvalidBodySize),
+            (
+                &container(&[(0, 0x80, 0, &[STOP])], &[], &[1], 2),
+                EofError::TruncatedData,
+            ),
+            (
+                &container(&[(0, 0, 0, &[STOP])], &[], &[], 0),
+                EofError::InvalidTypes,
+            ),
+        ];
+        for (code, expected) in cases {
+            assert_eq!(
+                Container::validate(code).map(|_| ()),
+                Err(expected),
+                "{code:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn code_sections_are_validated() {
+        let cases: [(&[Section], _); 9] = [
+            (
+                &[(0, 0x80, 0, &[Opcode::Jump as u8])],
+                EofError::UndefinedInstruction,
+            ),
+            (&[(0, 0x80, 0, &[RJUMP, 0])], EofError::TruncatedImmediate),
+            (
+                &[(0, 0x80, 0, &[RJUMP, 0, 1, STOP])],
+                EofError::InvalidRelativeJump,
+            ),
+            (
+                &[(0, 0x80, 0, &[PUSH0])],
+                EofError::NoTerminatingInstruction,
+            ),
+            (&[(0, 0x80, 0, &[POP, STOP])], EofError::StackUnderflow),
+            (
+                &[(0, 0x80, 0, &[PUSH0, STOP])],
+                EofError::InvalidMaxStackIncrease,
+            ),
+            (
+                &[(0, 0x80, 2, &[PUSH0, PUSH0, RJUMPI, 0xff, 0xfb, STOP])],
+                EofError::StackHeightMismatch,
+            ),
+            (&[(0, 0x80, 0, &[STOP, STOP])], EofError::UnreachableCode),
+            (
+                &[(0, 0x80, 0, &[CALLF, 0, 1, STOP]), (0, 0x80, 0, &[STOP])],
+                EofError::CallfToNonReturning,
+            ),
+        ];
+        for (sections, expected) in cases {
+            assert_eq!(validate(sections), Err(expected), "{sections:?}");
+        }
+        assert_eq!(
+            validate(&[(0, 0x80, 0, &[STOP]), (0, 0, 0, &[RETF])]),
+            Err(EofError::UnreachableCodeSection)
+        );
+        assert_eq!(
+            validate(&[(0, 0x80, 0, &[CALLF, 0, 1, STOP]), (0, 0, 0, &[STOP])]),
+            Err(EofError::InvalidNonReturningFlag)
+        );
+        assert_eq!(
+            validate(&[(0, 0x80, 1, &[PUSH0, RJUMPI, 0, 1, STOP, RJUMP, 0xff, 0xf8])]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn subcontainers_must_be_referenced_by_one_kind() {
+        let runtime = container(&[(0, 0x80, 0, &[STOP])], &[], &[], 0);
+        let eofcreate = [PUSH0, PUSH0, PUSH0, PUSH0, Opcode::EofCreate as u8, 0, STOP];
+        let initcode = container(&[(0, 0x80, 4, &eofcreate)], &[&runtime], &[], 0);
+        assert_eq!(
+            Container::validate(&initcode).map(|_| ()),
+            Err(EofError::IncompatibleContainerKind)
+        );
+
+        let returncontract = [PUSH0, PUSH0, Opcode::ReturnContract as u8, 0];
+        let initcode = container(&[(0, 0x80, 2, &returncontract)], &[&runtime], &[], 0);
+        let code = container(&[(0, 0x80, 4, &eofcreate)], &[&initcode], &[], 0);
+        assert!(Container::validate(&code).is_ok());
+
+        let code = container(&[(0, 0x80, 0, &[STOP])], &[&initcode], &[], 0);
+        assert_eq!(
+            Container::validate(&code).map(|_| ()),
+            Err(EofError::UnreferencedSubcontainer)
+        );
+    }
+
+    #[test]
+    fn aux_data_is_appended_to_data_section() {
+        let runtime = container(&[(0, 0x80, 0, &[STOP])], &[], &[1], 3);
+        let deployed = append_aux_data(&runtime, &[2, 3, 4]).unwrap();
+        assert_eq!(
+            deployed,
+            container(&[(0, 0x80, 0, &[STOP])], &[], &[1, 2, 3, 4], 4)
+        );
+        assert!(Container::validate(&deployed).is_ok());
+        assert_eq!(
+            append_aux_data(&runtime, &[2]),
+            Err(EofError::TruncatedData)
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::{error::Error, fmt};
+
+use evmc_vm::StatusCode;
+
+use crate::{
+    interpreter::eof::EofError,
+    types::{u256, Opcode},
+};
+
+/// Why an opcode failed. Handlers return only the reason, the interpreter loop adds the location
+/// to form an [`ExecutionError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureReason {
+    OutOfGas,
+    /// The accessed memory range does not fit into 64 bits, so expanding the memory can never be
+    /// paid for.
+    MemoryOverflow {
+        offset: u256,
+        len: u256,
+    },
+    /// The accessed range of the call data or the return data of the last call is out of bounds.
+    InvalidMemoryAccess {
+        offset: u256,
+        len: u256,
+    },
+    StackOverflow,
+    StackUnderflow,
+    BadJumpDestination(u256),
+    InvalidInstruction,
+    UndefinedInstruction,
+    StaticModeViolation,
+    /// A successful CREATE or CREATE2 did not return the address of the new contract.
+    MissingCreateAddress,
+    /// An EXTCALL, EXTDELEGATECALL or EXTSTATICCALL to an address with non zero high bytes.
+    AddressOutOfRange(u256),
+    /// The code starts with the EOF magic but is not a valid EOF container.
+    InvalidContainer(EofError),
+    /// Stepping would start or end inside of a function called with CALLF. The return stack is not
+    /// part of the state passed in and returned by stepping, so the function could not return.
+    UnsteppableReturnStack,
+}
+
+impl FailureReason {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::OutOfGas | Self::MemoryOverflow { .. } => StatusCode::EVMC_OUT_OF_GAS,
+            Self::InvalidMemoryAccess { .. } => StatusCode::EVMC_INVALID_MEMORY_ACCESS,
+            Self::StackOverflow => StatusCode::EVMC_STACK_OVERFLOW,
+            Self::StackUnderflow => StatusCode::EVMC_STACK_UNDERFLOW,
+            Self::BadJumpDestination(_) => StatusCode::EVMC_BAD_JUMP_DESTINATION,
+            Self::InvalidInstruction => StatusCode::EVMC_INVALID_INSTRUCTION,
+            Self::UndefinedInstruction => StatusCode::EVMC_UNDEFINED_INSTRUCTION,
+            Self::StaticModeViolation => StatusCode::EVMC_STATIC_MODE_VIOLATION,
+            Self::MissingCreateAddress => StatusCode::EVMC_INTERNAL_ERROR,
+            Self::AddressOutOfRange(_) => StatusCode::EVMC_ARGUMENT_OUT_OF_RANGE,
+            Self::InvalidContainer(_) => StatusCode::EVMC_CONTRACT_VALIDATION_FAILURE,
+            Self::UnsteppableReturnStack => StatusCode::EVMC_INTERNAL_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfGas => f.write_str("out of gas"),
+            Self::MemoryOverflow { offset, len } => {
+                write!(
+                    f,
+                    "memory access at offset {offset} with length {len} overflows"
+                )
+            }
+            Self::InvalidMemoryAccess { offset, len } => {
+                write!(
+                    f,
+                    "access at offset {offset} with length {len} is out of bounds"
+                )
+            }
+            Self::StackOverflow => f.write_str("stack overflow"),
+            Self::StackUnderflow => f.write_str("stack underflow"),
+            Self::BadJumpDestination(dest) => write!(f, "bad jump destination {dest}"),
+            Self::InvalidInstruction => f.write_str("invalid instruction"),
+            Self::UndefinedInstruction => f.write_str("undefined instruction"),
+            Self::StaticModeViolation => f.write_str("state modification in static call"),
+            Self::MissingCreateAddress => f.write_str("create did not return an address"),
+            Self::AddressOutOfRange(addr) => write!(f, "address {addr} is out of range"),
+            Self::InvalidContainer(error) => write!(f, "invalid EOF container: {error}"),
+            Self::UnsteppableReturnStack => {
+                f.write_str("return stack can not be carried across steps")
+            }
+        }
+    }
+}
+
+/// A failed execution together with the location of the failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionError {
+    /// `None` if the pc does not point to an opcode, e.g. because it points into push data.
+    pub opcode: Option<Opcode>,
+    pub pc: usize,
+    pub gas_left: u64,
+    pub reason: FailureReason,
+}
+
+impl ExecutionError {
+    /// The EVMC status code which is reported for this error.
+    pub fn status_code(&self) -> StatusCode {
+        self.reason.status_code()
+    }
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at pc {}", self.reason, self.pc)?;
+        if let Some(opcode) = self.opcode {
+            write!(f, " ({opcode:?})")?;
+        }
+        write!(f, " with {} gas left", self.gas_left)
+    }
+}
+
+impl Error for ExecutionError {}
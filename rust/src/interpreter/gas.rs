@@ -1,17 +1,15 @@
-use evmc_vm::{AccessStatus, Address, ExecutionContext, Revision, StatusCode, StepStatusCode};
+use evmc_vm::{AccessStatus, Address, ExecutionMessage, Revision};
 
-use crate::{interpreter::word_size, types::u256};
+use crate::{
+    host::Host,
+    interpreter::{error::FailureReason, tx_context::TxContextCache, word_size},
+    types::{u256, Opcode},
+};
 
-pub const OUT_OF_GAS_ERR: Result<(), (StepStatusCode, StatusCode)> = Err((
-    StepStatusCode::EVMC_STEP_FAILED,
-    StatusCode::EVMC_OUT_OF_GAS,
-));
+pub const OUT_OF_GAS_ERR: Result<(), FailureReason> = Err(FailureReason::OutOfGas);
 
 #[inline(always)]
-pub(super) fn consume_gas(
-    gas_left: &mut u64,
-    gas: u64,
-) -> Result<(), (StepStatusCode, StatusCode)> {
+pub(super) fn consume_gas(gas_left: &mut u64, gas: u64) -> Result<(), FailureReason> {
     if *gas_left < gas {
         return OUT_OF_GAS_ERR;
     }
@@ -23,40 +21,70 @@ pub(super) fn consume_gas(
 pub(super) fn consume_positive_value_cost(
     value: &u256,
     gas_left: &mut u64,
-) -> Result<(), (StepStatusCode, StatusCode)> {
+) -> Result<(), FailureReason> {
     if *value != u256::ZERO {
         consume_gas(gas_left, 9000)?;
     }
     Ok(())
 }
 
+/// Before Spurious Dragon (EIP-161) the cost is charged for every call to an account which does
+/// not exist, regardless of the value.
 #[inline(always)]
 pub(super) fn consume_value_to_empty_account_cost(
     value: &u256,
     addr: &Address,
-    context: &mut ExecutionContext,
+    host: &mut dyn Host,
     gas_left: &mut u64,
-) -> Result<(), (StepStatusCode, StatusCode)> {
-    if *value != u256::ZERO && !context.account_exists(addr) {
+    revision: Revision,
+) -> Result<(), FailureReason> {
+    if (*value != u256::ZERO || revision < Revision::EVMC_SPURIOUS_DRAGON)
+        && !host.account_exists(addr)
+    {
         consume_gas(gas_left, 25000)?;
     }
     Ok(())
 }
 
+/// The static cost of opcodes which access other accounts or storage before Berlin, where it was
+/// replaced by the cost of cold and warm accesses (EIP-2929). It was raised in Tangerine Whistle
+/// (EIP-150) and in Istanbul (EIP-1884).
+#[inline(always)]
+pub(super) fn pre_berlin_access_cost(op: Opcode, revision: Revision) -> u64 {
+    let tangerine_whistle = revision >= Revision::EVMC_TANGERINE_WHISTLE;
+    let istanbul = revision >= Revision::EVMC_ISTANBUL;
+    match op {
+        Opcode::Balance if istanbul => 700,
+        Opcode::Balance if tangerine_whistle => 400,
+        Opcode::Balance => 20,
+        Opcode::ExtCodeSize | Opcode::ExtCodeCopy if tangerine_whistle => 700,
+        Opcode::ExtCodeSize | Opcode::ExtCodeCopy => 20,
+        Opcode::ExtCodeHash if istanbul => 700,
+        Opcode::ExtCodeHash => 400,
+        Opcode::SLoad if istanbul => 800,
+        Opcode::SLoad if tangerine_whistle => 200,
+        Opcode::SLoad => 50,
+        Opcode::Call | Opcode::CallCode | Opcode::DelegateCall | Opcode::StaticCall
+            if tangerine_whistle =>
+        {
+            700
+        }
+        Opcode::Call | Opcode::CallCode | Opcode::DelegateCall | Opcode::StaticCall => 40,
+        _ => 0,
+    }
+}
+
 #[inline(always)]
 pub(super) fn consume_address_access_cost(
     gas_left: &mut u64,
     addr: &Address,
-    context: &mut ExecutionContext,
+    message: &ExecutionMessage,
+    host: &mut dyn Host,
+    tx_context: &mut TxContextCache,
     revision: Revision,
-) -> Result<(), (StepStatusCode, StatusCode)> {
-    let tx_context = context.get_tx_context();
+) -> Result<(), FailureReason> {
     if revision >= Revision::EVMC_BERLIN {
-        if *addr != tx_context.tx_origin
-            //&& addr != tx_context.tx_to // TODO
-            && !(revision >= Revision::EVMC_SHANGHAI && *addr == tx_context.block_coinbase)
-            && context.access_account(addr) == AccessStatus::EVMC_ACCESS_COLD
-        {
+        if access_is_cold(addr, message, host, tx_context, revision) {
             consume_gas(gas_left, 2600)?;
         } else {
             consume_gas(gas_left, 100)?;
@@ -65,12 +93,51 @@ pub(super) fn consume_address_access_cost(
     Ok(())
 }
 
+/// Accesses `addr` and returns whether it was cold. The addresses which are warm from the start of
+/// the transaction (EIP-2929, EIP-3651) are never cold, even if the host does not know about them.
+#[inline(always)]
+pub(super) fn access_is_cold(
+    addr: &Address,
+    message: &ExecutionMessage,
+    host: &mut dyn Host,
+    tx_context: &mut TxContextCache,
+    revision: Revision,
+) -> bool {
+    let tx_context = tx_context.get(host);
+    // tx.to is the recipient of the outermost frame. In nested frames the interpreter does not know
+    // tx.to, so the host has to report it as warm (see `InMemoryHost::begin_transaction`).
+    *addr != tx_context.tx_origin
+        && addr != message.recipient()
+        && !(revision >= Revision::EVMC_SHANGHAI && *addr == tx_context.block_coinbase)
+        && !is_precompile(addr, revision)
+        && host.access_account(addr) == AccessStatus::EVMC_ACCESS_COLD
+}
+
+/// Returns whether `addr` is a precompiled contract in `revision`.
+#[inline(always)]
+fn is_precompile(addr: &Address, revision: Revision) -> bool {
+    let [prefix @ .., high, low] = addr.bytes;
+    if prefix != [0; 18] {
+        return false;
+    }
+    let addr = u16::from_be_bytes([high, low]);
+    let last = if revision >= Revision::EVMC_PRAGUE {
+        0x11 // BLS12-381 operations
+    } else if revision >= Revision::EVMC_CANCUN {
+        0x0a // point evaluation
+    } else if revision >= Revision::EVMC_ISTANBUL {
+        0x09 // blake2f
+    } else if revision >= Revision::EVMC_BYZANTIUM {
+        0x08 // modexp and alt_bn128 operations
+    } else {
+        0x04
+    };
+    (1..=last).contains(&addr) || (revision >= Revision::EVMC_OSAKA && addr == 0x100)
+}
+
 /// consume 3 * minimum_word_size
 #[inline(always)]
-pub(super) fn consume_copy_cost(
-    gas_left: &mut u64,
-    len: u64,
-) -> Result<(), (StepStatusCode, StatusCode)> {
+pub(super) fn consume_copy_cost(gas_left: &mut u64, len: u64) -> Result<(), FailureReason> {
     let (cost, cost_overflow) = word_size(len)?.overflowing_mul(3);
     if cost_overflow {
         return OUT_OF_GAS_ERR;
@@ -0,0 +1,2815 @@
+use std::{
+    borrow::Cow,
+    cmp::{max, min},
+    mem,
+};
+
+use evmc_vm::{
+    AccessStatus, Address, ExecutionMessage, MessageFlags, MessageKind, Revision, StatusCode,
+    StepStatusCode, StorageStatus, Uint256,
+};
+
+use crate::{
+    host::Host,
+    interpreter::{
+        checks::*,
+        eof::{self, Container},
+        error::FailureReason,
+        gas::*,
+        memory::access_len,
+        sha3_cache::keccak256,
+        stack::STACK_SIZE_LIMIT,
+        word_size, Interpreter, REVISIONS,
+    },
+    types::{u256, Opcode},
+};
+
+pub type OpResult = Result<(), FailureReason>;
+
+/// The maximum depth of nested calls and creates.
+const CALL_DEPTH_LIMIT: i32 = 1024;
+
+/// A handler executes a single opcode. Handlers of opcodes which are part of the static table in
+/// `blocks` can rely on the stack bounds, the static gas and the revision being checked already.
+pub type OpFn = fn(&mut Interpreter) -> OpResult;
+
+pub type InstructionTable = [OpFn; 256];
+
+static INSTRUCTION_TABLES: [InstructionTable; REVISIONS.len()] = [
+    build_instruction_table::<{ Revision::EVMC_FRONTIER as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_HOMESTEAD as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_TANGERINE_WHISTLE as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_SPURIOUS_DRAGON as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_BYZANTIUM as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_CONSTANTINOPLE as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_PETERSBURG as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_ISTANBUL as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_BERLIN as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_LONDON as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_PARIS as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_SHANGHAI as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_CANCUN as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_PRAGUE as u8 }>(),
+    build_instruction_table::<{ Revision::EVMC_OSAKA as u8 }>(),
+];
+
+/// Returns the opcode handlers for the revision. Opcodes which are not available in the revision
+/// fail with EVMC_UNDEFINED_INSTRUCTION.
+pub fn instruction_table<const REVISION: u8>() -> &'static InstructionTable {
+    &INSTRUCTION_TABLES[REVISION as usize]
+}
+
+const fn build_instruction_table<const REVISION: u8>() -> InstructionTable {
+    let mut table = [op_undefined as OpFn; 256];
+    table[Opcode::Stop as usize] = op_stop;
+    table[Opcode::Add as usize] = op_add;
+    table[Opcode::Mul as usize] = op_mul;
+    table[Opcode::Sub as usize] = op_sub;
+    table[Opcode::Div as usize] = op_div;
+    table[Opcode::SDiv as usize] = op_sdiv;
+    table[Opcode::Mod as usize] = op_mod;
+    table[Opcode::SMod as usize] = op_smod;
+    table[Opcode::AddMod as usize] = op_addmod;
+    table[Opcode::MulMod as usize] = op_mulmod;
+    table[Opcode::Exp as usize] = op_exp::<REVISION>;
+    table[Opcode::SignExtend as usize] = op_signextend;
+    table[Opcode::Lt as usize] = op_lt;
+    table[Opcode::Gt as usize] = op_gt;
+    table[Opcode::SLt as usize] = op_slt;
+    table[Opcode::SGt as usize] = op_sgt;
+    table[Opcode::Eq as usize] = op_eq;
+    table[Opcode::IsZero as usize] = op_iszero;
+    table[Opcode::And as usize] = op_and;
+    table[Opcode::Or as usize] = op_or;
+    table[Opcode::Xor as usize] = op_xor;
+    table[Opcode::Not as usize] = op_not;
+    table[Opcode::Byte as usize] = op_byte;
+    table[Opcode::Sha3 as usize] = op_sha3;
+    table[Opcode::Address as usize] = op_address;
+    table[Opcode::Balance as usize] = op_balance::<REVISION>;
+    table[Opcode::Origin as usize] = op_origin;
+    table[Opcode::Caller as usize] = op_caller;
+    table[Opcode::CallValue as usize] = op_callvalue;
+    table[Opcode::CallDataLoad as usize] = op_calldataload;
+    table[Opcode::CallDataSize as usize] = op_calldatasize;
+    table[Opcode::CallDataCopy as usize] = op_calldatacopy;
+    table[Opcode::CodeSize as usize] = op_codesize;
+    table[Opcode::CodeCopy as usize] = op_codecopy;
+    table[Opcode::GasPrice as usize] = op_gasprice;
+    table[Opcode::ExtCodeSize as usize] = op_extcodesize::<REVISION>;
+    table[Opcode::ExtCodeCopy as usize] = op_extcodecopy::<REVISION>;
+    table[Opcode::BlockHash as usize] = op_blockhash;
+    table[Opcode::Coinbase as usize] = op_coinbase;
+    table[Opcode::Timestamp as usize] = op_timestamp;
+    table[Opcode::Number as usize] = op_number;
+    table[Opcode::PrevRandao as usize] = op_prevrandao;
+    table[Opcode::GasLimit as usize] = op_gaslimit;
+    table[Opcode::Pop as usize] = op_pop;
+    table[Opcode::MLoad as usize] = op_mload;
+    table[Opcode::MStore as usize] = op_mstore;
+    table[Opcode::MStore8 as usize] = op_mstore8;
+    table[Opcode::SLoad as usize] = op_sload::<REVISION>;
+    table[Opcode::SStore as usize] = op_sstore::<REVISION>;
+    table[Opcode::Jump as usize] = op_jump;
+    table[Opcode::JumpI as usize] = op_jumpi;
+    table[Opcode::Pc as usize] = op_pc;
+    table[Opcode::MSize as usize] = op_msize;
+    table[Opcode::Gas as usize] = op_gas;
+    table[Opcode::JumpDest as usize] = op_jumpdest;
+    table[Opcode::Return as usize] = op_return;
+    table[Opcode::SelfDestruct as usize] = op_selfdestruct::<REVISION>;
+    table[Opcode::Push1 as usize] = op_push::<1>;
+    table[Opcode::Push2 as usize] = op_push::<2>;
+    table[Opcode::Push3 as usize] = op_push::<3>;
+    table[Opcode::Push4 as usize] = op_push::<4>;
+    table[Opcode::Push5 as usize] = op_push::<5>;
+    table[Opcode::Push6 as usize] = op_push::<6>;
+    table[Opcode::Push7 as usize] = op_push::<7>;
+    table[Opcode::Push8 as usize] = op_push::<8>;
+    table[Opcode::Push9 as usize] = op_push::<9>;
+    table[Opcode::Push10 as usize] = op_push::<10>;
+    table[Opcode::Push11 as usize] = op_push::<11>;
+    table[Opcode::Push12 as usize] = op_push::<12>;
+    table[Opcode::Push13 as usize] = op_push::<13>;
+    table[Opcode::Push14 as usize] = op_push::<14>;
+    table[Opcode::Push15 as usize] = op_push::<15>;
+    table[Opcode::Push16 as usize] = op_push::<16>;
+    table[Opcode::Push17 as usize] = op_push::<17>;
+    table[Opcode::Push18 as usize] = op_push::<18>;
+    table[Opcode::Push19 as usize] = op_push::<19>;
+    table[Opcode::Push20 as usize] = op_push::<20>;
+    table[Opcode::Push21 as usize] = op_push::<21>;
+    table[Opcode::Push22 as usize] = op_push::<22>;
+    table[Opcode::Push23 as usize] = op_push::<23>;
+    table[Opcode::Push24 as usize] = op_push::<24>;
+    table[Opcode::Push25 as usize] = op_push::<25>;
+    table[Opcode::Push26 as usize] = op_push::<26>;
+    table[Opcode::Push27 as usize] = op_push::<27>;
+    table[Opcode::Push28 as usize] = op_push::<28>;
+    table[Opcode::Push29 as usize] = op_push::<29>;
+    table[Opcode::Push30 as usize] = op_push::<30>;
+    table[Opcode::Push31 as usize] = op_push::<31>;
+    table[Opcode::Push32 as usize] = op_push::<32>;
+    table[Opcode::Dup1 as usize] = op_dup::<1>;
+    table[Opcode::Dup2 as usize] = op_dup::<2>;
+    table[Opcode::Dup3 as usize] = op_dup::<3>;
+    table[Opcode::Dup4 as usize] = op_dup::<4>;
+    table[Opcode::Dup5 as usize] = op_dup::<5>;
+    table[Opcode::Dup6 as usize] = op_dup::<6>;
+    table[Opcode::Dup7 as usize] = op_dup::<7>;
+    table[Opcode::Dup8 as usize] = op_dup::<8>;
+    table[Opcode::Dup9 as usize] = op_dup::<9>;
+    table[Opcode::Dup10 as usize] = op_dup::<10>;
+    table[Opcode::Dup11 as usize] = op_dup::<11>;
+    table[Opcode::Dup12 as usize] = op_dup::<12>;
+    table[Opcode::Dup13 as usize] = op_dup::<13>;
+    table[Opcode::Dup14 as usize] = op_dup::<14>;
+    table[Opcode::Dup15 as usize] = op_dup::<15>;
+    table[Opcode::Dup16 as usize] = op_dup::<16>;
+    table[Opcode::Swap1 as usize] = op_swap::<1>;
+    table[Opcode::Swap2 as usize] = op_swap::<2>;
+    table[Opcode::Swap3 as usize] = op_swap::<3>;
+    table[Opcode::Swap4 as usize] = op_swap::<4>;
+    table[Opcode::Swap5 as usize] = op_swap::<5>;
+    table[Opcode::Swap6 as usize] = op_swap::<6>;
+    table[Opcode::Swap7 as usize] = op_swap::<7>;
+    table[Opcode::Swap8 as usize] = op_swap::<8>;
+    table[Opcode::Swap9 as usize] = op_swap::<9>;
+    table[Opcode::Swap10 as usize] = op_swap::<10>;
+    table[Opcode::Swap11 as usize] = op_swap::<11>;
+    table[Opcode::Swap12 as usize] = op_swap::<12>;
+    table[Opcode::Swap13 as usize] = op_swap::<13>;
+    table[Opcode::Swap14 as usize] = op_swap::<14>;
+    table[Opcode::Swap15 as usize] = op_swap::<15>;
+    table[Opcode::Swap16 as usize] = op_swap::<16>;
+    table[Opcode::Log0 as usize] = op_log::<REVISION, 0>;
+    table[Opcode::Log1 as usize] = op_log::<REVISION, 1>;
+    table[Opcode::Log2 as usize] = op_log::<REVISION, 2>;
+    table[Opcode::Log3 as usize] = op_log::<REVISION, 3>;
+    table[Opcode::Log4 as usize] = op_log::<REVISION, 4>;
+    table[Opcode::Create as usize] = op_create::<REVISION, false>;
+    table[Opcode::Call as usize] = op_call::<REVISION, false>;
+    table[Opcode::CallCode as usize] = op_call::<REVISION, true>;
+    table[Opcode::Invalid as usize] = op_invalid;
+    if REVISION >= Revision::EVMC_HOMESTEAD as u8 {
+        table[Opcode::DelegateCall as usize] = op_static_delegate_call::<REVISION, true>;
+    }
+    if REVISION >= Revision::EVMC_BYZANTIUM as u8 {
+        table[Opcode::ReturnDataSize as usize] = op_returndatasize;
+        table[Opcode::ReturnDataCopy as usize] = op_returndatacopy;
+        table[Opcode::Revert as usize] = op_revert;
+        table[Opcode::StaticCall as usize] = op_static_delegate_call::<REVISION, false>;
+    }
+    if REVISION >= Revision::EVMC_CONSTANTINOPLE as u8 {
+        table[Opcode::Shl as usize] = op_shl;
+        table[Opcode::Shr as usize] = op_shr;
+        table[Opcode::Sar as usize] = op_sar;
+        table[Opcode::ExtCodeHash as usize] = op_extcodehash::<REVISION>;
+        table[Opcode::Create2 as usize] = op_create::<REVISION, true>;
+    }
+    if REVISION >= Revision::EVMC_ISTANBUL as u8 {
+        table[Opcode::ChainId as usize] = op_chainid;
+        table[Opcode::SelfBalance as usize] = op_selfbalance;
+    }
+    if REVISION >= Revision::EVMC_LONDON as u8 {
+        table[Opcode::BaseFee as usize] = op_basefee;
+    }
+    if REVISION >= Revision::EVMC_SHANGHAI as u8 {
+        table[Opcode::Push0 as usize] = op_push0;
+    }
+    if REVISION >= Revision::EVMC_CANCUN as u8 {
+        table[Opcode::BlobHash as usize] = op_blobhash;
+        table[Opcode::BlobBaseFee as usize] = op_blobbasefee;
+        table[Opcode::TLoad as usize] = op_tload;
+        table[Opcode::TStore as usize] = op_tstore::<REVISION>;
+        table[Opcode::MCopy as usize] = op_mcopy;
+    }
+    if REVISION >= Revision::EVMC_OSAKA as u8 {
+        table[Opcode::DataLoad as usize] = op_dataload;
+        table[Opcode::DataLoadN as usize] = op_dataloadn;
+        table[Opcode::DataSize as usize] = op_datasize;
+        table[Opcode::DataCopy as usize] = op_datacopy;
+        table[Opcode::RJump as usize] = op_rjump;
+        table[Opcode::RJumpI as usize] = op_rjumpi;
+        table[Opcode::RJumpV as usize] = op_rjumpv;
+        table[Opcode::CallF as usize] = op_callf;
+        table[Opcode::RetF as usize] = op_retf;
+        table[Opcode::JumpF as usize] = op_jumpf;
+        table[Opcode::DupN as usize] = op_dupn;
+        table[Opcode::SwapN as usize] = op_swapn;
+        table[Opcode::Exchange as usize] = op_exchange;
+        table[Opcode::EofCreate as usize] = op_eofcreate::<REVISION>;
+        table[Opcode::ReturnContract as usize] = op_returncontract;
+        table[Opcode::ReturnDataLoad as usize] = op_returndataload;
+        table[Opcode::ExtCall as usize] = op_extcall::<REVISION>;
+        table[Opcode::ExtDelegateCall as usize] = op_ext_static_delegate_call::<REVISION, true>;
+        table[Opcode::ExtStaticCall as usize] = op_ext_static_delegate_call::<REVISION, false>;
+    }
+    table
+}
+
+fn op_undefined(_state: &mut Interpreter) -> OpResult {
+    Err(FailureReason::UndefinedInstruction)
+}
+
+fn op_stop(state: &mut Interpreter) -> OpResult {
+    state.step_status_code = StepStatusCode::EVMC_STEP_STOPPED;
+    state.status_code = StatusCode::EVMC_SUCCESS;
+    Ok(())
+}
+
+fn op_add(state: &mut Interpreter) -> OpResult {
+    let [value1, value2] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value1 + value2);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_mul(state: &mut Interpreter) -> OpResult {
+    let [fac1, fac2] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(fac1 * fac2);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_sub(state: &mut Interpreter) -> OpResult {
+    let [value1, value2] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value1 - value2);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_div(state: &mut Interpreter) -> OpResult {
+    let [value, denominator] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value / denominator);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_sdiv(state: &mut Interpreter) -> OpResult {
+    let [value, denominator] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value.sdiv(denominator));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_mod(state: &mut Interpreter) -> OpResult {
+    let [value, denominator] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value % denominator);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_smod(state: &mut Interpreter) -> OpResult {
+    let [value, denominator] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value.srem(denominator));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_addmod(state: &mut Interpreter) -> OpResult {
+    let [value1, value2, denominator] = state.stack.pop_unchecked();
+    state
+        .stack
+        .push_unchecked(u256::addmod(value1, value2, denominator));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_mulmod(state: &mut Interpreter) -> OpResult {
+    let [fac1, fac2, denominator] = state.stack.pop_unchecked();
+    state
+        .stack
+        .push_unchecked(u256::mulmod(fac1, fac2, denominator));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_exp<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    let [value, exp] = state.stack.pop_unchecked();
+    let byte_size = 32 - exp.into_iter().take_while(|byte| *byte == 0).count() as u64;
+    // The cost per byte was raised in Spurious Dragon (EIP-160).
+    let byte_cost = if revision >= Revision::EVMC_SPURIOUS_DRAGON {
+        50
+    } else {
+        10
+    };
+    consume_gas(&mut state.gas_left, byte_size * byte_cost)?; // * does not overflow
+    state.stack.push_unchecked(value.pow(exp));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_signextend(state: &mut Interpreter) -> OpResult {
+    let [size, value] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(u256::signextend(size, value));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_lt(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs < rhs);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_gt(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs > rhs);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_slt(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs.slt(&rhs));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_sgt(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs.sgt(&rhs));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_eq(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs == rhs);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_iszero(state: &mut Interpreter) -> OpResult {
+    let [value] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value == u256::ZERO);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_and(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs & rhs);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_or(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs | rhs);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_xor(state: &mut Interpreter) -> OpResult {
+    let [lhs, rhs] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(lhs ^ rhs);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_not(state: &mut Interpreter) -> OpResult {
+    let [value] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(!value);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_byte(state: &mut Interpreter) -> OpResult {
+    let [offset, value] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value.byte(offset));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_shl(state: &mut Interpreter) -> OpResult {
+    let [shift, value] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value << shift);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_shr(state: &mut Interpreter) -> OpResult {
+    let [shift, value] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value >> shift);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_sar(state: &mut Interpreter) -> OpResult {
+    let [shift, value] = state.stack.pop_unchecked();
+    state.stack.push_unchecked(value.sar(shift));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_sha3(state: &mut Interpreter) -> OpResult {
+    let [offset, len] = state.stack.pop_unchecked();
+
+    let len = access_len(offset, len)?;
+    consume_gas(&mut state.gas_left, 6 * word_size(len)?)?; // * does not overflow
+
+    let data = state.memory.get_slice(offset, len, &mut state.gas_left)?;
+    let hash = match state.sha3_cache {
+        Some(sha3_cache) => sha3_cache.hash(data),
+        None => keccak256(data),
+    };
+    state.stack.push_unchecked(hash);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_address(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(state.message.recipient());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_balance<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    if revision < Revision::EVMC_BERLIN {
+        consume_gas(
+            &mut state.gas_left,
+            pre_berlin_access_cost(Opcode::Balance, revision),
+        )?;
+    }
+    let [addr] = state.stack.pop()?;
+    let addr = addr.into();
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &addr,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    state.stack.push(state.host.get_balance(&addr))?;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_origin(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).tx_origin);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_caller(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(state.message.sender());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_callvalue(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(*state.message.value());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_calldataload(state: &mut Interpreter) -> OpResult {
+    let [offset] = state.stack.pop_unchecked();
+    let (offset, overflow) = offset.into_u64_with_overflow();
+    let offset = offset as usize;
+    let call_data = state.message.input().map(|v| v.as_slice()).unwrap_or(&[]);
+    if overflow || offset >= call_data.len() {
+        state.stack.push_unchecked(u256::ZERO);
+    } else {
+        let end = min(call_data.len(), offset + 32);
+        let mut bytes = [0; 32];
+        bytes[..end - offset].copy_from_slice(&call_data[offset..end]);
+        state.stack.push_unchecked(bytes);
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_calldatasize(state: &mut Interpreter) -> OpResult {
+    let call_data_len = state
+        .message
+        .input()
+        .map(|call_data| call_data.len())
+        .unwrap_or(0);
+    state.stack.push_unchecked(call_data_len);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_push0(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(u256::ZERO);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_calldatacopy(state: &mut Interpreter) -> OpResult {
+    let [dest_offset, offset, len] = state.stack.pop_unchecked();
+
+    if len != u256::ZERO {
+        let (len_u64, len_overflow) = len.into_u64_with_overflow();
+        if len_overflow {
+            return Err(FailureReason::InvalidMemoryAccess { offset, len });
+        }
+        let len = len_u64;
+
+        let src = state.message.input().map(|v| v.as_slice()).unwrap_or(&[]);
+        let src = get_slice_within_bounds(src, offset, len);
+        let dest = state
+            .memory
+            .get_slice(dest_offset, len, &mut state.gas_left)?;
+        copy_slice_padded(src, dest, &mut state.gas_left)?;
+    }
+
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_codesize(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(state.code_state.code_len());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_codecopy(state: &mut Interpreter) -> OpResult {
+    let [dest_offset, offset, len] = state.stack.pop_unchecked();
+
+    if len != u256::ZERO {
+        let len = access_len(dest_offset, len)?;
+
+        let src = get_slice_within_bounds(&state.code_state, offset, len);
+        let dest = state
+            .memory
+            .get_slice(dest_offset, len, &mut state.gas_left)?;
+        copy_slice_padded(src, dest, &mut state.gas_left)?;
+    }
+
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_gasprice(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).tx_gas_price);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_extcodesize<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    if revision < Revision::EVMC_BERLIN {
+        consume_gas(
+            &mut state.gas_left,
+            pre_berlin_access_cost(Opcode::ExtCodeSize, revision),
+        )?;
+    }
+    let [addr] = state.stack.pop()?;
+    let addr = addr.into();
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &addr,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    if is_eof_contract(state.host, &addr, revision) {
+        state.stack.push(EOF_MAGIC.len())?;
+    } else {
+        state.stack.push(state.host.get_code_size(&addr))?;
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_extcodecopy<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    if revision < Revision::EVMC_BERLIN {
+        consume_gas(
+            &mut state.gas_left,
+            pre_berlin_access_cost(Opcode::ExtCodeCopy, revision),
+        )?;
+    }
+    let [addr, dest_offset, offset, len] = state.stack.pop()?;
+    let addr = addr.into();
+
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &addr,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    if len != u256::ZERO {
+        let len = access_len(dest_offset, len)?;
+
+        let dest = state
+            .memory
+            .get_slice(dest_offset, len, &mut state.gas_left)?;
+        let (offset, offset_overflow) = offset.into_u64_with_overflow();
+        consume_copy_cost(&mut state.gas_left, len)?;
+        let bytes_written = if is_eof_contract(state.host, &addr, revision) {
+            let src = EOF_MAGIC.get(offset as usize..).unwrap_or_default();
+            let len = min(src.len(), dest.len());
+            dest[..len].copy_from_slice(&src[..len]);
+            len
+        } else {
+            state.host.copy_code(&addr, offset as usize, dest)
+        };
+        if offset_overflow {
+            zero_slice(dest);
+        } else if (bytes_written as u64) < len {
+            zero_slice(&mut dest[bytes_written..]);
+        }
+    }
+
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_returndatasize(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(
+        state
+            .last_call_return_data
+            .as_ref()
+            .map(|data| data.len())
+            .unwrap_or_default(),
+    );
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_returndatacopy(state: &mut Interpreter) -> OpResult {
+    let [dest_offset, offset, len] = state.stack.pop_unchecked();
+
+    let src = state.last_call_return_data.as_deref().unwrap_or(&[]);
+    // EOF code reads the return data out of bounds as zeros (EIP-7069).
+    if state.code_state.container().is_none() {
+        let (offset_u64, offset_overflow) = offset.into_u64_with_overflow();
+        let (len_u64, len_overflow) = len.into_u64_with_overflow();
+        let (end, end_overflow) = offset_u64.overflowing_add(len_u64);
+        if offset_overflow || len_overflow || end_overflow || end > src.len() as u64 {
+            return Err(FailureReason::InvalidMemoryAccess { offset, len });
+        }
+    }
+    let len = access_len(dest_offset, len)?;
+
+    if len != 0 {
+        let src = get_slice_within_bounds(src, offset, len);
+        let dest = state
+            .memory
+            .get_slice(dest_offset, len, &mut state.gas_left)?;
+        copy_slice_padded(src, dest, &mut state.gas_left)?;
+    }
+
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_extcodehash<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    if revision < Revision::EVMC_BERLIN {
+        consume_gas(
+            &mut state.gas_left,
+            pre_berlin_access_cost(Opcode::ExtCodeHash, revision),
+        )?;
+    }
+    let [addr] = state.stack.pop()?;
+    let addr = addr.into();
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &addr,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    if is_eof_contract(state.host, &addr, revision) {
+        state.stack.push(keccak256(&EOF_MAGIC))?;
+    } else {
+        state.stack.push(state.host.get_code_hash(&addr))?;
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_blockhash(state: &mut Interpreter) -> OpResult {
+    let [block_number] = state.stack.pop_unchecked();
+    let (idx, idx_overflow) = block_number.into_u64_with_overflow();
+    if idx_overflow {
+        state.stack.push_unchecked(u256::ZERO);
+    } else {
+        state
+            .stack
+            .push_unchecked(state.host.get_block_hash(idx as i64));
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_coinbase(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).block_coinbase);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_timestamp(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).block_timestamp as u64);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_number(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).block_number as u64);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_prevrandao(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).block_prev_randao);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_gaslimit(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).block_gas_limit as u64);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_chainid(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).chain_id);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_selfbalance(state: &mut Interpreter) -> OpResult {
+    let addr = state.message.recipient();
+    if u256::from(addr) == u256::ZERO {
+        state.stack.push_unchecked(u256::ZERO);
+    } else {
+        state.stack.push_unchecked(state.host.get_balance(addr));
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_basefee(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).block_base_fee);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_blobhash(state: &mut Interpreter) -> OpResult {
+    let [idx] = state.stack.pop_unchecked();
+    let (idx, idx_overflow) = idx.into_u64_with_overflow();
+    let idx = idx as usize;
+    let hashes = &state.tx_context.get(state.host).blob_hashes;
+    if !idx_overflow && idx < hashes.len() {
+        state.stack.push_unchecked(hashes[idx]);
+    } else {
+        state.stack.push_unchecked(u256::ZERO);
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_blobbasefee(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.tx_context.get(state.host).blob_base_fee);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_pop(state: &mut Interpreter) -> OpResult {
+    let [_] = state.stack.pop_unchecked();
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_mload(state: &mut Interpreter) -> OpResult {
+    let [offset] = state.stack.pop_unchecked();
+
+    state
+        .stack
+        .push_unchecked(state.memory.get_word(offset, &mut state.gas_left)?);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_mstore(state: &mut Interpreter) -> OpResult {
+    let [offset, value] = state.stack.pop_unchecked();
+
+    let dest = state.memory.get_slice(offset, 32, &mut state.gas_left)?;
+    dest.copy_from_slice(value.as_slice());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_mstore8(state: &mut Interpreter) -> OpResult {
+    let [offset, value] = state.stack.pop_unchecked();
+
+    let dest = state.memory.get_byte(offset, &mut state.gas_left)?;
+    *dest = value[31];
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_sload<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    if revision < Revision::EVMC_BERLIN {
+        consume_gas(
+            &mut state.gas_left,
+            pre_berlin_access_cost(Opcode::SLoad, revision),
+        )?;
+    }
+    let [key] = state.stack.pop()?;
+    let key = key.into();
+    let addr = state.message.recipient();
+    if revision >= Revision::EVMC_BERLIN {
+        if state.host.access_storage(addr, &key) == AccessStatus::EVMC_ACCESS_COLD {
+            consume_gas(&mut state.gas_left, 2100)?;
+        } else {
+            consume_gas(&mut state.gas_left, 100)?;
+        }
+    }
+    let value = state.host.get_storage(addr, &key);
+    state.stack.push(value)?;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_sstore<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    check_not_read_only(state.message, revision)?;
+    if revision >= Revision::EVMC_ISTANBUL && state.gas_left <= 2300 {
+        OUT_OF_GAS_ERR?;
+    }
+    let [key, value] = state.stack.pop()?;
+    let key = key.into();
+    let addr = state.message.recipient();
+
+    let (dyn_gas_1, dyn_gas_2, dyn_gas_3, refund_1, refund_2, refund_3) =
+        if revision >= Revision::EVMC_LONDON {
+            (100, 2900, 20000, 5000 - 2100 - 100, 4800, 20000 - 100)
+        } else if revision >= Revision::EVMC_BERLIN {
+            (100, 2900, 20000, 5000 - 2100 - 100, 15000, 20000 - 100)
+        } else if revision >= Revision::EVMC_ISTANBUL {
+            (800, 5000, 20000, 4200, 15000, 19200)
+        } else {
+            // Constantinople (EIP-1283). Other revisions use the legacy costs below.
+            (200, 5000, 20000, 4800, 15000, 19800)
+        };
+
+    // dyn gas
+    // if Z == Y
+    //     dyn_gas_1 =  100                                 800
+    // else if Y == X
+    //     if X == 0
+    //         dyn_gas_3 = 20000
+    //     else
+    //         dyn_gas_2 = 2900                             5000
+    // else
+    //     dyn_gas_1 = 100                                  800
+
+    // gas refunds
+    //if z != y
+    //    if y == x
+    //        if x != 0 and z == 0
+    //            gas_refunds_2 += 4800                     15000
+    //    else
+    //        if x != 0
+    //            if y == 0
+    //                gas_refunds_2 -= 4800                 15000
+    //            else if z == 0
+    //                gas_refunds_2 += 4800                 15000
+    //        if z == x
+    //            if x == 0
+    //                gas_refunds_3 += 20000 - 100          19200
+    //            else
+    //                gas_refunds_1 += 5000 - 2100 - 100    4200
+
+    let status = state.host.set_storage(addr, &key, &value.into());
+    let net_gas_metering =
+        revision >= Revision::EVMC_ISTANBUL || revision == Revision::EVMC_CONSTANTINOPLE;
+    let (mut dyn_gas, gas_refund_change) = if net_gas_metering {
+        match status {
+            StorageStatus::EVMC_STORAGE_ASSIGNED => (dyn_gas_1, 0),
+            StorageStatus::EVMC_STORAGE_ADDED => (dyn_gas_3, 0),
+            StorageStatus::EVMC_STORAGE_DELETED => (dyn_gas_2, refund_2),
+            StorageStatus::EVMC_STORAGE_MODIFIED => (dyn_gas_2, 0),
+            StorageStatus::EVMC_STORAGE_DELETED_ADDED => (dyn_gas_1, -refund_2),
+            StorageStatus::EVMC_STORAGE_MODIFIED_DELETED => (dyn_gas_1, refund_2),
+            StorageStatus::EVMC_STORAGE_DELETED_RESTORED => (dyn_gas_1, -refund_2 + refund_1),
+            StorageStatus::EVMC_STORAGE_ADDED_DELETED => (dyn_gas_1, refund_3),
+            StorageStatus::EVMC_STORAGE_MODIFIED_RESTORED => (dyn_gas_1, refund_1),
+        }
+    } else {
+        // Without net gas metering the cost only depends on the current and the new value.
+        match status {
+            StorageStatus::EVMC_STORAGE_ADDED
+            | StorageStatus::EVMC_STORAGE_DELETED_ADDED
+            | StorageStatus::EVMC_STORAGE_DELETED_RESTORED => (20000, 0),
+            StorageStatus::EVMC_STORAGE_DELETED
+            | StorageStatus::EVMC_STORAGE_MODIFIED_DELETED
+            | StorageStatus::EVMC_STORAGE_ADDED_DELETED => (5000, 15000),
+            StorageStatus::EVMC_STORAGE_ASSIGNED
+            | StorageStatus::EVMC_STORAGE_MODIFIED
+            | StorageStatus::EVMC_STORAGE_MODIFIED_RESTORED => (5000, 0),
+        }
+    };
+    if revision >= Revision::EVMC_BERLIN
+        && state.host.access_storage(addr, &key) == AccessStatus::EVMC_ACCESS_COLD
+    {
+        dyn_gas += 2100;
+    }
+    consume_gas(&mut state.gas_left, dyn_gas)?;
+    state.gas_refund += gas_refund_change;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_jump(state: &mut Interpreter) -> OpResult {
+    let [dest] = state.stack.pop_unchecked();
+    state.code_state.try_jump(dest)?;
+    Ok(())
+}
+
+fn op_jumpi(state: &mut Interpreter) -> OpResult {
+    let [dest, cond] = state.stack.pop_unchecked();
+    if cond == u256::ZERO {
+        state.code_state.next();
+    } else {
+        state.code_state.try_jump(dest)?;
+    }
+    Ok(())
+}
+
+fn op_pc(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(state.code_state.pc());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_msize(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(state.memory.len());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_gas(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(state.gas_left);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_jumpdest(state: &mut Interpreter) -> OpResult {
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_tload(state: &mut Interpreter) -> OpResult {
+    let [key] = state.stack.pop_unchecked();
+    let addr = state.message.recipient();
+    let value = state.host.get_transient_storage(addr, &key.into());
+    state.stack.push_unchecked(value);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_tstore<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    check_not_read_only(state.message, revision)?;
+    consume_gas(&mut state.gas_left, 100)?;
+    let [key, value] = state.stack.pop()?;
+    let addr = state.message.recipient();
+    state
+        .host
+        .set_transient_storage(addr, &key.into(), &value.into());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_mcopy(state: &mut Interpreter) -> OpResult {
+    let [dest_offset, offset, len] = state.stack.pop_unchecked();
+    if len != u256::ZERO {
+        state
+            .memory
+            .copy_within(offset, dest_offset, len, &mut state.gas_left)?;
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_return(state: &mut Interpreter) -> OpResult {
+    let [offset, len] = state.stack.pop()?;
+    let len = access_len(offset, len)?;
+    let data = state.memory.get_slice(offset, len, &mut state.gas_left)?;
+    state.output = Some(data.to_owned());
+    state.step_status_code = StepStatusCode::EVMC_STEP_RETURNED;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_revert(state: &mut Interpreter) -> OpResult {
+    let [offset, len] = state.stack.pop()?;
+    let len = access_len(offset, len)?;
+    let data = state.memory.get_slice(offset, len, &mut state.gas_left)?;
+    // The host reverts the state changes. The refund counter is relative to the current frame, so
+    // discarding the refunds of the frame resets it to its value at frame entry, which is 0.
+    state.gas_refund = 0;
+    state.output = Some(data.to_owned());
+    state.step_status_code = StepStatusCode::EVMC_STEP_REVERTED;
+    state.status_code = StatusCode::EVMC_REVERT;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_invalid(_state: &mut Interpreter) -> OpResult {
+    Err(FailureReason::InvalidInstruction)
+}
+
+fn op_selfdestruct<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    check_not_read_only(state.message, revision)?;
+    if revision >= Revision::EVMC_TANGERINE_WHISTLE {
+        consume_gas(&mut state.gas_left, 5000)?;
+    }
+    let [addr] = state.stack.pop()?;
+    let addr = addr.into();
+
+    if revision >= Revision::EVMC_BERLIN
+        && access_is_cold(
+            &addr,
+            state.message,
+            state.host,
+            &mut state.tx_context,
+            revision,
+        )
+    {
+        consume_gas(&mut state.gas_left, 2600)?;
+    }
+
+    // Before Tangerine Whistle (EIP-150) there is no cost for new accounts. Before Spurious Dragon
+    // (EIP-161) it is charged regardless of the balance.
+    let new_account = if revision >= Revision::EVMC_SPURIOUS_DRAGON {
+        u256::from(state.host.get_balance(state.message.recipient())) > u256::ZERO
+            && !state.host.account_exists(&addr)
+    } else {
+        revision >= Revision::EVMC_TANGERINE_WHISTLE && !state.host.account_exists(&addr)
+    };
+    if new_account {
+        consume_gas(&mut state.gas_left, 25000)?;
+    }
+
+    let destructed = state.host.selfdestruct(state.message.recipient(), &addr);
+    if revision <= Revision::EVMC_BERLIN && destructed {
+        state.gas_refund += 24000;
+    }
+
+    state.step_status_code = StepStatusCode::EVMC_STEP_STOPPED;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_push<const N: usize>(state: &mut Interpreter) -> OpResult {
+    state
+        .stack
+        .push_unchecked(state.code_state.get_push_data(N));
+    Ok(())
+}
+
+fn op_dup<const N: usize>(state: &mut Interpreter) -> OpResult {
+    state.stack.push_unchecked(state.stack.nth_unchecked(N));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_swap<const N: usize>(state: &mut Interpreter) -> OpResult {
+    state.stack.swap_with_top_unchecked(N);
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_log<const REVISION: u8, const N: usize>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    check_not_read_only(state.message, revision)?;
+    consume_gas(&mut state.gas_left, 375)?;
+    let [offset, len] = state.stack.pop()?;
+    let topics: [u256; N] = state.stack.pop()?;
+    let len = access_len(offset, len)?;
+    let (len8, len8_overflow) = len.overflowing_mul(8);
+    let (cost, cost_overflow) = (375 * N as u64).overflowing_add(len8);
+    if len8_overflow || cost_overflow {
+        return OUT_OF_GAS_ERR;
+    }
+    consume_gas(&mut state.gas_left, cost)?;
+
+    let data = state.memory.get_slice(offset, len, &mut state.gas_left)?;
+    let topics: &[_; N] = unsafe { mem::transmute(&topics) };
+    state
+        .host
+        .emit_log(state.message.recipient(), data, topics.as_slice());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_create<const REVISION: u8, const CREATE2: bool>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    consume_gas(&mut state.gas_left, 32000)?;
+    check_not_read_only(state.message, revision)?;
+    let [value, offset, len] = state.stack.pop()?;
+    let salt = if CREATE2 {
+        state.stack.pop::<1>()?[0]
+    } else {
+        u256::ZERO // ignored
+    };
+    let len = access_len(offset, len)?;
+
+    let init_code_word_size = word_size(len)?;
+    if revision >= Revision::EVMC_SHANGHAI {
+        const MAX_INIT_CODE_LEN: u64 = 2 * 24576;
+        if len > MAX_INIT_CODE_LEN {
+            return OUT_OF_GAS_ERR;
+        }
+        let init_code_cost = 2 * init_code_word_size; // does not overflow
+        consume_gas(&mut state.gas_left, init_code_cost)?;
+    }
+    if CREATE2 {
+        let hash_cost = 6 * init_code_word_size; // does not overflow
+        consume_gas(&mut state.gas_left, hash_cost)?;
+    }
+
+    let init_code = state.memory.get_slice(offset, len, &mut state.gas_left)?;
+
+    if state.message.depth() >= CALL_DEPTH_LIMIT
+        || value > state.host.get_balance(state.message.recipient()).into()
+    {
+        return abort_call(state);
+    }
+
+    let gas_limit = if revision >= Revision::EVMC_TANGERINE_WHISTLE {
+        state.gas_left - state.gas_left / 64
+    } else {
+        state.gas_left
+    };
+    consume_gas(&mut state.gas_left, gas_limit)?;
+
+    let create_message = ExecutionMessage::new(
+        if CREATE2 {
+            MessageKind::EVMC_CREATE2
+        } else {
+            MessageKind::EVMC_CREATE
+        },
+        state.message.flags(),
+        state.message.depth() + 1,
+        gas_limit as i64,
+        u256::ZERO.into(), // ignored
+        *state.message.recipient(),
+        Some(init_code),
+        value.into(),
+        salt.into(),
+        u256::ZERO.into(), // ignored
+        None,
+    );
+    let result = state.host.call(&create_message);
+
+    state.gas_left += result.gas_left() as u64;
+    state.gas_refund += result.gas_refund();
+
+    if result.status_code() == StatusCode::EVMC_SUCCESS {
+        let Some(addr) = result.create_address() else {
+            return Err(FailureReason::MissingCreateAddress);
+        };
+
+        state.last_call_return_data = None;
+        state.stack.push(addr)?;
+    } else {
+        state.last_call_return_data = result.output().map(|output| Cow::Owned(output.to_owned()));
+        state.stack.push(u256::ZERO)?;
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_call<const REVISION: u8, const CODE: bool>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    if revision < Revision::EVMC_BERLIN {
+        consume_gas(
+            &mut state.gas_left,
+            pre_berlin_access_cost(Opcode::Call, revision),
+        )?;
+    }
+    let [gas, addr, value, args_offset, args_len, ret_offset, ret_len] = state.stack.pop()?;
+
+    if !CODE && value != u256::ZERO {
+        check_not_read_only(state.message, revision)?;
+    }
+
+    let addr = addr.into();
+    let args_len = access_len(args_offset, args_len)?;
+    let ret_len = access_len(ret_offset, ret_len)?;
+
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &addr,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    let code_addr = code_address(state, addr, revision)?;
+    // access slice to consume potential memory expansion cost but drop it so that we can get
+    // another mutable reference into memory for input
+    let _dest = state
+        .memory
+        .get_slice(ret_offset, ret_len, &mut state.gas_left)?;
+    let input = state
+        .memory
+        .get_slice(args_offset, args_len, &mut state.gas_left)?;
+    consume_positive_value_cost(&value, &mut state.gas_left)?;
+    if !CODE {
+        consume_value_to_empty_account_cost(
+            &value,
+            &addr,
+            state.host,
+            &mut state.gas_left,
+            revision,
+        )?;
+    }
+
+    let endowment = call_endowment(gas, state.gas_left, revision)?;
+    let stipend = if value == u256::ZERO { 0 } else { 2300 };
+    state.gas_left += stipend;
+
+    if state.message.depth() >= CALL_DEPTH_LIMIT
+        || value > u256::from(state.host.get_balance(state.message.recipient()))
+    {
+        return abort_call(state);
+    }
+
+    let call_message = if CODE {
+        ExecutionMessage::new(
+            MessageKind::EVMC_CALLCODE,
+            state.message.flags(),
+            state.message.depth() + 1,
+            (endowment + stipend) as i64,
+            *state.message.recipient(),
+            *state.message.recipient(),
+            Some(input),
+            value.into(),
+            u256::ZERO.into(), // ignored
+            code_addr,
+            None,
+        )
+    } else {
+        ExecutionMessage::new(
+            MessageKind::EVMC_CALL,
+            state.message.flags(),
+            state.message.depth() + 1,
+            (endowment + stipend) as i64,
+            addr,
+            *state.message.recipient(),
+            Some(input),
+            value.into(),
+            u256::ZERO.into(), // ignored
+            code_addr,
+            None,
+        )
+    };
+
+    let result = state.host.call(&call_message);
+    state.last_call_return_data = result.output().map(|output| Cow::Owned(output.to_owned()));
+    let dest = state
+        .memory
+        .get_slice(ret_offset, ret_len, &mut state.gas_left)?;
+    if let Some(output) = &state.last_call_return_data {
+        let min_len = min(output.len(), ret_len as usize); // ret_len == dest.len()
+        dest[..min_len].copy_from_slice(&output[..min_len]);
+    }
+
+    state.gas_left += result.gas_left() as u64;
+    consume_gas(&mut state.gas_left, endowment)?;
+    consume_gas(&mut state.gas_left, stipend)?;
+    state.gas_refund += result.gas_refund();
+
+    state
+        .stack
+        .push(result.status_code() == StatusCode::EVMC_SUCCESS)?;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_static_delegate_call<const REVISION: u8, const DELEGATE: bool>(
+    state: &mut Interpreter,
+) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    if revision < Revision::EVMC_BERLIN {
+        consume_gas(
+            &mut state.gas_left,
+            pre_berlin_access_cost(Opcode::DelegateCall, revision),
+        )?;
+    }
+    let [gas, addr, args_offset, args_len, ret_offset, ret_len] = state.stack.pop()?;
+
+    let addr = addr.into();
+    let args_len = access_len(args_offset, args_len)?;
+    let ret_len = access_len(ret_offset, ret_len)?;
+
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &addr,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    let code_addr = code_address(state, addr, revision)?;
+    // access slice to consume potential memory expansion cost but drop it so that we can get
+    // another mutable reference into memory for input
+    let _dest = state
+        .memory
+        .get_slice(ret_offset, ret_len, &mut state.gas_left)?;
+    let input = state
+        .memory
+        .get_slice(args_offset, args_len, &mut state.gas_left)?;
+
+    let endowment = call_endowment(gas, state.gas_left, revision)?;
+
+    if state.message.depth() >= CALL_DEPTH_LIMIT {
+        return abort_call(state);
+    }
+
+    let call_message = if DELEGATE {
+        ExecutionMessage::new(
+            MessageKind::EVMC_DELEGATECALL,
+            state.message.flags(),
+            state.message.depth() + 1,
+            endowment as i64,
+            *state.message.recipient(),
+            *state.message.sender(),
+            Some(input),
+            *state.message.value(),
+            u256::ZERO.into(), // ignored
+            code_addr,
+            None,
+        )
+    } else {
+        ExecutionMessage::new(
+            MessageKind::EVMC_CALL,
+            MessageFlags::EVMC_STATIC as u32,
+            state.message.depth() + 1,
+            (endowment) as i64,
+            addr,
+            *state.message.recipient(),
+            Some(input),
+            u256::ZERO.into(), // ignored
+            u256::ZERO.into(), // ignored
+            code_addr,
+            None,
+        )
+    };
+
+    let result = state.host.call(&call_message);
+    state.last_call_return_data = result.output().map(|output| Cow::Owned(output.to_owned()));
+    let dest = state
+        .memory
+        .get_slice(ret_offset, ret_len, &mut state.gas_left)?;
+    if let Some(output) = &state.last_call_return_data {
+        let min_len = min(output.len(), ret_len as usize); // ret_len == dest.len()
+        dest[..min_len].copy_from_slice(&output[..min_len]);
+    }
+
+    state.gas_left += result.gas_left() as u64;
+    consume_gas(&mut state.gas_left, endowment)?;
+    state.gas_refund += result.gas_refund();
+
+    state
+        .stack
+        .push(result.status_code() == StatusCode::EVMC_SUCCESS)?;
+    state.code_state.next();
+    Ok(())
+}
+
+/// The maximum depth of nested CALLFs (EIP-4750).
+const RETURN_STACK_LIMIT: usize = 1024;
+
+/// Returns the container of the code. EOF opcodes are only decoded in EOF code, so their handlers
+/// can rely on it being there.
+#[inline(always)]
+fn container<'a>(state: &Interpreter<'a, '_>) -> Result<&'a Container, FailureReason> {
+    state
+        .code_state
+        .container()
+        .ok_or(FailureReason::UndefinedInstruction)
+}
+
+/// Returns the 32 bytes of `data` at `offset`. Bytes after the end of `data` are zero.
+#[inline(always)]
+fn load_word(data: &[u8], offset: u256) -> u256 {
+    let src = get_slice_within_bounds(data, offset, 32);
+    let mut bytes = [0; 32];
+    bytes[..src.len()].copy_from_slice(src);
+    bytes.into()
+}
+
+fn op_dataload(state: &mut Interpreter) -> OpResult {
+    let [offset] = state.stack.pop_unchecked();
+    let data = container(state)?.data();
+    state.stack.push_unchecked(load_word(data, offset));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_dataloadn(state: &mut Interpreter) -> OpResult {
+    let offset = state.code_state.immediate();
+    let data = container(state)?.data();
+    state.stack.push_unchecked(load_word(data, offset.into()));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_datasize(state: &mut Interpreter) -> OpResult {
+    let data = container(state)?.data();
+    state.stack.push_unchecked(data.len());
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_datacopy(state: &mut Interpreter) -> OpResult {
+    let [dest_offset, offset, len] = state.stack.pop_unchecked();
+    let data = container(state)?.data();
+    let len = access_len(dest_offset, len)?;
+    if len != 0 {
+        let src = get_slice_within_bounds(data, offset, len);
+        let dest = state
+            .memory
+            .get_slice(dest_offset, len, &mut state.gas_left)?;
+        copy_slice_padded(src, dest, &mut state.gas_left)?;
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+// The targets of relative jumps were checked when the container was validated and resolved to
+// instruction indices by the code analysis.
+
+fn op_rjump(state: &mut Interpreter) -> OpResult {
+    state.code_state.jump_to(state.code_state.immediate());
+    Ok(())
+}
+
+fn op_rjumpi(state: &mut Interpreter) -> OpResult {
+    let [condition] = state.stack.pop_unchecked();
+    if condition == u256::ZERO {
+        state.code_state.next();
+    } else {
+        state.code_state.jump_to(state.code_state.immediate());
+    }
+    Ok(())
+}
+
+fn op_rjumpv(state: &mut Interpreter) -> OpResult {
+    let [case] = state.stack.pop_unchecked();
+    let jump_table = state.code_state.jump_table();
+    let (case, case_overflow) = case.into_u64_with_overflow();
+    match jump_table.get(case as usize) {
+        Some(target) if !case_overflow => state.code_state.jump_to(*target as usize),
+        _ => state.code_state.next(),
+    }
+    Ok(())
+}
+
+fn op_callf(state: &mut Interpreter) -> OpResult {
+    consume_gas(&mut state.gas_left, 5)?;
+    let section = state.code_state.immediate();
+    let code_type = container(state)?.code_type(section);
+    if state.return_stack.len() >= RETURN_STACK_LIMIT
+        || state.stack.len() + code_type.max_stack_increase as usize > STACK_SIZE_LIMIT
+    {
+        return Err(FailureReason::StackOverflow);
+    }
+    state.return_stack.push(state.code_state.index() + 1);
+    state.code_state.jump_to_section(section);
+    Ok(())
+}
+
+fn op_retf(state: &mut Interpreter) -> OpResult {
+    consume_gas(&mut state.gas_left, 3)?;
+    // The return stack is empty if stepping started inside of a called code section.
+    let Some(index) = state.return_stack.pop() else {
+        return Err(FailureReason::UnsteppableReturnStack);
+    };
+    state.code_state.jump_to(index);
+    Ok(())
+}
+
+fn op_jumpf(state: &mut Interpreter) -> OpResult {
+    consume_gas(&mut state.gas_left, 5)?;
+    let section = state.code_state.immediate();
+    let code_type = container(state)?.code_type(section);
+    if state.stack.len() + code_type.max_stack_increase as usize > STACK_SIZE_LIMIT {
+        return Err(FailureReason::StackOverflow);
+    }
+    state.code_state.jump_to_section(section);
+    Ok(())
+}
+
+fn op_dupn(state: &mut Interpreter) -> OpResult {
+    consume_gas(&mut state.gas_left, 3)?;
+    let n = state.code_state.immediate() + 1;
+    state.stack.push(state.stack.nth(n)?)?;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_swapn(state: &mut Interpreter) -> OpResult {
+    consume_gas(&mut state.gas_left, 3)?;
+    let n = state.code_state.immediate() + 1;
+    state.stack.swap_with_top(n)?;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_exchange(state: &mut Interpreter) -> OpResult {
+    consume_gas(&mut state.gas_left, 3)?;
+    let immediate = state.code_state.immediate();
+    let n = (immediate >> 4) + 1;
+    let m = (immediate & 0x0f) + 1;
+    state.stack.exchange(n, m)?;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_eofcreate<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let revision = REVISIONS[REVISION as usize];
+    check_not_read_only(state.message, revision)?;
+    consume_gas(&mut state.gas_left, 32000)?;
+    let [value, salt, input_offset, input_len] = state.stack.pop()?;
+    let init_container = container(state)?.container(state.code_state.immediate());
+    let input_len = access_len(input_offset, input_len)?;
+    let input = state
+        .memory
+        .get_slice(input_offset, input_len, &mut state.gas_left)?;
+    let hash_cost = 6 * word_size(init_container.len() as u64)?; // does not overflow
+    consume_gas(&mut state.gas_left, hash_cost)?;
+
+    if state.message.depth() >= CALL_DEPTH_LIMIT
+        || value > state.host.get_balance(state.message.recipient()).into()
+    {
+        state.last_call_return_data = None;
+        state.stack.push(u256::ZERO)?;
+        state.code_state.next();
+        return Ok(());
+    }
+
+    let gas_limit = state.gas_left - state.gas_left / 64;
+    consume_gas(&mut state.gas_left, gas_limit)?;
+
+    let create_message = ExecutionMessage::new(
+        MessageKind::EVMC_EOFCREATE,
+        state.message.flags(),
+        state.message.depth() + 1,
+        gas_limit as i64,
+        u256::ZERO.into(), // ignored
+        *state.message.recipient(),
+        Some(input),
+        value.into(),
+        salt.into(),
+        u256::ZERO.into(), // ignored
+        Some(init_container),
+    );
+    let result = state.host.call(&create_message);
+
+    state.gas_left += result.gas_left() as u64;
+    state.gas_refund += result.gas_refund();
+
+    if result.status_code() == StatusCode::EVMC_SUCCESS {
+        let Some(addr) = result.create_address() else {
+            return Err(FailureReason::MissingCreateAddress);
+        };
+
+        state.last_call_return_data = None;
+        state.stack.push(addr)?;
+    } else {
+        state.last_call_return_data = result.output().map(|output| Cow::Owned(output.to_owned()));
+        state.stack.push(u256::ZERO)?;
+    }
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_returncontract(state: &mut Interpreter) -> OpResult {
+    let [offset, len] = state.stack.pop()?;
+    let runtime_container = container(state)?.container(state.code_state.immediate());
+    let len = access_len(offset, len)?;
+    let aux_data = state.memory.get_slice(offset, len, &mut state.gas_left)?;
+    let deployed = eof::append_aux_data(runtime_container, aux_data)
+        .map_err(FailureReason::InvalidContainer)?;
+    state.output = Some(deployed);
+    state.step_status_code = StepStatusCode::EVMC_STEP_RETURNED;
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_returndataload(state: &mut Interpreter) -> OpResult {
+    let [offset] = state.stack.pop_unchecked();
+    let data = state.last_call_return_data.as_deref().unwrap_or(&[]);
+    state.stack.push_unchecked(load_word(data, offset));
+    state.code_state.next();
+    Ok(())
+}
+
+fn op_extcall<const REVISION: u8>(state: &mut Interpreter) -> OpResult {
+    let [addr, input_offset, input_len, value] = state.stack.pop()?;
+    if value != u256::ZERO {
+        check_not_read_only(state.message, REVISIONS[REVISION as usize])?;
+    }
+    let call = ExtCall {
+        kind: MessageKind::EVMC_CALL,
+        is_static: false,
+        value,
+    };
+    ext_call::<REVISION>(state, call, addr, input_offset, input_len)
+}
+
+fn op_ext_static_delegate_call<const REVISION: u8, const DELEGATE: bool>(
+    state: &mut Interpreter,
+) -> OpResult {
+    let [addr, input_offset, input_len] = state.stack.pop()?;
+    let call = if DELEGATE {
+        ExtCall {
+            kind: MessageKind::EVMC_DELEGATECALL,
+            is_static: false,
+            value: (*state.message.value()).into(),
+        }
+    } else {
+        ExtCall {
+            kind: MessageKind::EVMC_CALL,
+            is_static: true,
+            value: u256::ZERO,
+        }
+    };
+    ext_call::<REVISION>(state, call, addr, input_offset, input_len)
+}
+
+/// What distinguishes EXTCALL, EXTDELEGATECALL and EXTSTATICCALL.
+struct ExtCall {
+    kind: MessageKind,
+    is_static: bool,
+    value: u256,
+}
+
+/// The part of EXTCALL, EXTDELEGATECALL and EXTSTATICCALL (EIP-7069) after the arguments were
+/// popped. Instead of the gas, the callee gets all but `max(gas_left / 64, 5000)` of the gas left.
+/// Calls which can not be executed fail lightly by pushing 1, like a revert, without consuming the
+/// gas which would have been forwarded.
+#[inline(always)]
+fn ext_call<const REVISION: u8>(
+    state: &mut Interpreter,
+    call: ExtCall,
+    addr: u256,
+    input_offset: u256,
+    input_len: u256,
+) -> OpResult {
+    const MIN_RETAINED_GAS: u64 = 5000;
+    const MIN_CALLEE_GAS: u64 = 2300;
+    let revision = REVISIONS[REVISION as usize];
+    let delegate = call.kind == MessageKind::EVMC_DELEGATECALL;
+    let value = call.value;
+
+    let addr_bytes = Uint256::from(addr).bytes;
+    if addr_bytes[..12] != [0; 12] {
+        return Err(FailureReason::AddressOutOfRange(addr));
+    }
+    let addr = Address {
+        bytes: addr_bytes[12..].try_into().unwrap(),
+    };
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &addr,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    let code_addr = code_address(state, addr, revision)?;
+    let input_len = access_len(input_offset, input_len)?;
+    let input = state
+        .memory
+        .get_slice(input_offset, input_len, &mut state.gas_left)?;
+    if !delegate {
+        consume_positive_value_cost(&value, &mut state.gas_left)?;
+        consume_value_to_empty_account_cost(
+            &value,
+            &addr,
+            state.host,
+            &mut state.gas_left,
+            revision,
+        )?;
+    }
+
+    let gas_limit = state
+        .gas_left
+        .saturating_sub(max(state.gas_left / 64, MIN_RETAINED_GAS));
+    if gas_limit < MIN_CALLEE_GAS
+        || state.message.depth() >= CALL_DEPTH_LIMIT
+        || (!delegate && value > u256::from(state.host.get_balance(state.message.recipient())))
+        || (delegate && !is_eof_contract(state.host, &code_addr, revision))
+    {
+        state.last_call_return_data = None;
+        state.stack.push(1u8)?;
+        state.code_state.next();
+        return Ok(());
+    }
+
+    let call_message = ExecutionMessage::new(
+        call.kind,
+        if call.is_static {
+            MessageFlags::EVMC_STATIC as u32
+        } else {
+            state.message.flags()
+        },
+        state.message.depth() + 1,
+        gas_limit as i64,
+        if delegate {
+            *state.message.recipient()
+        } else {
+            addr
+        },
+        if delegate {
+            *state.message.sender()
+        } else {
+            *state.message.recipient()
+        },
+        Some(input),
+        value.into(),
+        u256::ZERO.into(), // ignored
+        code_addr,
+        None,
+    );
+    consume_gas(&mut state.gas_left, gas_limit)?;
+    let result = state.host.call(&call_message);
+    state.last_call_return_data = result.output().map(|output| Cow::Owned(output.to_owned()));
+    state.gas_left += result.gas_left() as u64;
+    state.gas_refund += result.gas_refund();
+
+    let status: u8 = match result.status_code() {
+        StatusCode::EVMC_SUCCESS => 0,
+        StatusCode::EVMC_REVERT => 1,
+        _ => 2,
+    };
+    state.stack.push(status)?;
+    state.code_state.next();
+    Ok(())
+}
+
+/// Returns whether the code of `addr` is an EOF container. Before Osaka all code is legacy code.
+#[inline(always)]
+fn is_eof_contract(host: &dyn Host, addr: &Address, revision: Revision) -> bool {
+    if revision < Revision::EVMC_OSAKA {
+        return false;
+    }
+    let mut magic = [0; EOF_MAGIC.len()];
+    host.copy_code(addr, 0, &mut magic) == magic.len() && Container::is_eof(&magic)
+}
+
+/// Legacy code can not inspect the code of EOF contracts. EXTCODESIZE, EXTCODECOPY and EXTCODEHASH
+/// treat their code as if it were only the EOF magic (EIP-3540).
+const EOF_MAGIC: [u8; 2] = [0xef, 0x00];
+
+/// Returns the address whose code is executed by a call to `addr`. From Prague an account can
+/// delegate to the code of another account (EIP-7702), in which case accessing the delegate costs
+/// extra gas. Delegations are not followed transitively.
+#[inline(always)]
+fn code_address(
+    state: &mut Interpreter,
+    addr: Address,
+    revision: Revision,
+) -> Result<Address, FailureReason> {
+    if revision < Revision::EVMC_PRAGUE {
+        return Ok(addr);
+    }
+    let Some(delegate) = state.host.get_delegate(&addr) else {
+        return Ok(addr);
+    };
+    consume_address_access_cost(
+        &mut state.gas_left,
+        &delegate,
+        state.message,
+        state.host,
+        &mut state.tx_context,
+        revision,
+    )?;
+    Ok(delegate)
+}
+
+/// Returns the gas passed to a call. Before Tangerine Whistle (EIP-150) the requested gas has to be
+/// available, afterwards it is capped at all but one 64th of the gas left.
+#[inline(always)]
+fn call_endowment(gas: u256, gas_left: u64, revision: Revision) -> Result<u64, FailureReason> {
+    let gas = gas.into_u64_saturating();
+    if revision >= Revision::EVMC_TANGERINE_WHISTLE {
+        Ok(min(gas, gas_left - gas_left / 64))
+    } else if gas > gas_left {
+        Err(FailureReason::OutOfGas)
+    } else {
+        Ok(gas)
+    }
+}
+
+/// Fails a call or create without executing it, e.g. because the call depth limit is reached. The
+/// gas which would have been forwarded is not consumed yet, so it stays available.
+fn abort_call(state: &mut Interpreter) -> OpResult {
+    state.last_call_return_data = None;
+    state.stack.push(u256::ZERO)?;
+    state.code_state.next();
+    Ok(())
+}
+
+#[inline(always)]
+fn get_slice_within_bounds<T>(data: &[T], offset: u256, len: u64) -> &[T] {
+    if len == 0 {
+        return &[];
+    }
+    let (offset, offset_overflow) = offset.into_u64_with_overflow();
+    if offset_overflow {
+        return &[];
+    }
+    let offset = offset as usize;
+    let len = len as usize;
+    let (end, end_overflow) = offset.overflowing_add(len);
+    if end_overflow || offset >= data.len() {
+        &[]
+    } else {
+        &data[offset..min(end, data.len())]
+    }
+}
+
+#[inline(always)]
+fn zero_slice(data: &mut [u8]) {
+    for byte in data {
+        *byte = 0;
+    }
+}
+
+#[inline(always)]
+fn copy_slice_padded(src: &[u8], dest: &mut [u8], gas_left: &mut u64) -> Result<(), FailureReason> {
+    consume_copy_cost(gas_left, dest.len() as u64)?;
+    dest[..src.len()].copy_from_slice(src);
+    zero_slice(&mut dest[src.len()..]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, collections::HashMap};
+
+    use evmc_vm::{
+        Address, Bytes32, ExecutionResult, ExecutionTxContext, Revision, StatusCode,
+        StepStatusCode, StorageStatus, Uint256,
+    };
+
+    use crate::{
+        host::{Account, Host, InMemoryHost},
+        interpreter::{
+            eof::{tests::container, EofError},
+            run,
+            sha3_cache::keccak256,
+            tests::message,
+            CodeAnalysis, CodeState, ExecutionError, FailureReason, Memory, Stack, REVISIONS,
+        },
+        types::{u256, Opcode},
+    };
+
+    /// Executes `code` as if a previous call returned `[1, 2, 3]`.
+    fn execute(
+        revision: Revision,
+        code: &[u8],
+        depth: i32,
+        host: &mut dyn Host,
+    ) -> ExecutionResult {
+        try_execute(revision, code, depth, host).unwrap()
+    }
+
+    fn try_execute(
+        revision: Revision,
+        code: &[u8],
+        depth: i32,
+        host: &mut dyn Host,
+    ) -> Result<ExecutionResult, ExecutionError> {
+        let message = message(depth);
+        let analysis = CodeAnalysis::for_revision(code, revision);
+        run(
+            revision,
+            &message,
+            host,
+            StepStatusCode::EVMC_STEP_RUNNING,
+            CodeState::new(code, &analysis, 0),
+            0,
+            Stack::new(Vec::new()),
+            Memory::new(Vec::new()),
+            Some(Cow::Borrowed(&[1, 2, 3])),
+            None,
+            false,
+            false,
+            None,
+        )
+        .map(Into::into)
+    }
+
+    #[test]
+    fn calls_fail_at_depth_limit() {
+        let zeros = |n| [Opcode::Push1 as u8, 0x00].repeat(n);
+        let cases = [
+            (
+                Opcode::Call,
+                Revision::EVMC_FRONTIER,
+                [zeros(5), vec![0x60, 0x42]].concat(),
+            ),
+            (
+                Opcode::CallCode,
+                Revision::EVMC_FRONTIER,
+                [zeros(5), vec![0x60, 0x42]].concat(),
+            ),
+            (
+                Opcode::DelegateCall,
+                Revision::EVMC_HOMESTEAD,
+                [zeros(4), vec![0x60, 0x42]].concat(),
+            ),
+            (
+                Opcode::StaticCall,
+                Revision::EVMC_BYZANTIUM,
+                [zeros(4), vec![0x60, 0x42]].concat(),
+            ),
+            (Opcode::Create, Revision::EVMC_FRONTIER, zeros(3)),
+            (Opcode::Create2, Revision::EVMC_CONSTANTINOPLE, zeros(4)),
+        ];
+        for (op, min_revision, args) in cases {
+            let mut code = args;
+            if !matches!(op, Opcode::Create | Opcode::Create2) {
+                code.extend([Opcode::Push2 as u8, 0xff, 0xff]); // gas
+            }
+            code.extend([
+                op as u8,
+                Opcode::ReturnDataSize as u8,
+                Opcode::Push1 as u8,
+                0x00,
+                Opcode::MStore as u8, // return data size at 0x00
+                Opcode::Push1 as u8,
+                0x20,
+                Opcode::MStore as u8, // result at 0x20
+                Opcode::Push1 as u8,
+                0x40,
+                Opcode::Push1 as u8,
+                0x00,
+                Opcode::Return as u8,
+            ]);
+            // RETURNDATASIZE is only available from Byzantium.
+            let min_revision = min_revision.max(Revision::EVMC_BYZANTIUM);
+            let revisions = REVISIONS
+                .into_iter()
+                .filter(|revision| *revision >= min_revision && *revision <= Revision::EVMC_CANCUN);
+            for revision in revisions {
+                let mut host = InMemoryHost::new(revision, Default::default());
+                let below_limit = execute(revision, &code, 1023, &mut host);
+                let mut host = InMemoryHost::new(revision, Default::default());
+                let at_limit = execute(revision, &code, 1024, &mut host);
+                assert_eq!(below_limit.status_code(), StatusCode::EVMC_SUCCESS);
+                assert_eq!(at_limit.status_code(), StatusCode::EVMC_SUCCESS);
+
+                let below_limit_output = below_limit.output().unwrap();
+                assert_ne!(below_limit_output[32..], [0; 32], "{op:?} {revision:?}");
+                // The result is 0 and the return data of the previous call is cleared.
+                assert_eq!(
+                    at_limit.output().map(Vec::as_slice),
+                    Some([0; 64].as_slice()),
+                    "{op:?} {revision:?}"
+                );
+                // The gas which would have been forwarded is not consumed.
+                assert_eq!(
+                    at_limit.gas_left(),
+                    below_limit.gas_left(),
+                    "{op:?} {revision:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn revert_discards_gas_refund() {
+        // (status, original, current and new value of the slot, refund in Istanbul, refund in
+        // Berlin, refund from London)
+        let cases = [
+            (StorageStatus::EVMC_STORAGE_ASSIGNED, 1u8, 1u8, 1u8, 0, 0, 0),
+            (StorageStatus::EVMC_STORAGE_ADDED, 0, 0, 1, 0, 0, 0),
+            (
+                StorageStatus::EVMC_STORAGE_DELETED,
+                1,
+                1,
+                0,
+                15000,
+                15000,
+                4800,
+            ),
+            (StorageStatus::EVMC_STORAGE_MODIFIED, 1, 1, 2, 0, 0, 0),
+            (
+                StorageStatus::EVMC_STORAGE_DELETED_ADDED,
+                1,
+                0,
+                2,
+                -15000,
+                -15000,
+                -4800,
+            ),
+            (
+                StorageStatus::EVMC_STORAGE_MODIFIED_DELETED,
+                1,
+                2,
+                0,
+                15000,
+                15000,
+                4800,
+            ),
+            (
+                StorageStatus::EVMC_STORAGE_DELETED_RESTORED,
+                1,
+                0,
+                1,
+                -10800,
+                -12200,
+                -2000,
+            ),
+            (
+                StorageStatus::EVMC_STORAGE_ADDED_DELETED,
+                0,
+                1,
+                0,
+                19200,
+                19900,
+                19900,
+            ),
+            (
+                StorageStatus::EVMC_STORAGE_MODIFIED_RESTORED,
+                1,
+                2,
+                1,
+                4200,
+                2800,
+                2800,
+            ),
+        ];
+        let code = |value: u8, halt: Opcode| {
+            [
+                Opcode::Push1 as u8,
+                value,
+                Opcode::Push1 as u8,
+                0x00,
+                Opcode::SStore as u8,
+                Opcode::Push1 as u8,
+                0x00,
+                Opcode::Push1 as u8,
+                0x00,
+                halt as u8,
+            ]
+        };
+        let revisions = REVISIONS.into_iter().filter(|revision| {
+            *revision >= Revision::EVMC_ISTANBUL && *revision <= Revision::EVMC_CANCUN
+        });
+        for revision in revisions {
+            for (status, original, current, new, istanbul, berlin, london) in cases {
+                let refund = if revision >= Revision::EVMC_LONDON {
+                    london
+                } else if revision >= Revision::EVMC_BERLIN {
+                    berlin
+                } else {
+                    istanbul
+                };
+                let (addr, key) = (Address::default(), Bytes32::default());
+                let host = || {
+                    let mut host = InMemoryHost::new(revision, Default::default());
+                    host.set_account(
+                        addr,
+                        Account {
+                            storage: HashMap::from([(key, u256::from(original).into())]),
+                            ..Default::default()
+                        },
+                    );
+                    host.set_storage(&addr, &key, &u256::from(current).into());
+                    host
+                };
+                assert_eq!(
+                    host().set_storage(&addr, &key, &u256::from(new).into()),
+                    status
+                );
+
+                let result = execute(revision, &code(new, Opcode::Return), 0, &mut host());
+                assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+                assert_eq!(result.gas_refund(), refund, "{status:?} {revision:?}");
+
+                let result = execute(revision, &code(new, Opcode::Revert), 0, &mut host());
+                assert_eq!(result.status_code(), StatusCode::EVMC_REVERT);
+                assert_eq!(result.gas_refund(), 0, "{status:?} {revision:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn failed_calls_discard_gas_refund() {
+        let callee = u256::from(0x42u8).into();
+        let code = [
+            [Opcode::Push1 as u8, 0x00].repeat(5),
+            vec![
+                Opcode::Push1 as u8,
+                0x42,
+                Opcode::Push2 as u8,
+                0xff,
+                0xff,
+                Opcode::Call as u8,
+                Opcode::Stop as u8,
+            ],
+        ]
+        .concat();
+        let clear_slot = [
+            Opcode::Push1 as u8,
+            0x00,
+            Opcode::Push1 as u8,
+            0x00,
+            Opcode::SStore as u8,
+        ];
+        let revert = [
+            Opcode::Push1 as u8,
+            0x00,
+            Opcode::Push1 as u8,
+            0x00,
+            Opcode::Revert as u8,
+        ];
+        let revisions = REVISIONS.into_iter().filter(|revision| {
+            *revision >= Revision::EVMC_ISTANBUL && *revision <= Revision::EVMC_CANCUN
+        });
+        for revision in revisions {
+            let refund = if revision >= Revision::EVMC_LONDON {
+                4800
+            } else {
+                15000
+            };
+            let halts = [
+                (&[Opcode::Stop as u8][..], refund),
+                (&revert[..], 0),
+                (&[Opcode::Invalid as u8][..], 0),
+            ];
+            for (halt, refund) in halts {
+                let mut host = InMemoryHost::new(revision, Default::default());
+                host.set_account(
+                    callee,
+                    Account {
+                        code: [clear_slot.as_slice(), halt].concat(),
+                        storage: HashMap::from([(u256::ZERO.into(), u256::from(1u8).into())]),
+                        ..Default::default()
+                    },
+                );
+                let result = execute(revision, &code, 0, &mut host);
+                assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+                assert_eq!(result.gas_refund(), refund, "{halt:?} {revision:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn prewarmed_addresses_are_warm() {
+        let origin = Address { bytes: [0xa0; 20] };
+        let coinbase = Address { bytes: [0xc0; 20] };
+        let recipient = Address::default();
+        let cold = Address { bytes: [0xdd; 20] };
+        let precompile = |n: u8| u256::from(n).into();
+
+        let push_addr = |addr: &Address| [&[Opcode::Push20 as u8][..], &addr.bytes].concat();
+        let zeros = |n| [Opcode::Push1 as u8, 0x00].repeat(n);
+        // (opcode, number of zero arguments pushed before and after the address)
+        let ops = [
+            (Opcode::Balance, 0, 0),
+            (Opcode::ExtCodeSize, 0, 0),
+            (Opcode::ExtCodeHash, 0, 0),
+            (Opcode::ExtCodeCopy, 3, 0),
+            (Opcode::Call, 5, 1),
+            (Opcode::CallCode, 5, 1),
+            (Opcode::DelegateCall, 4, 1),
+            (Opcode::StaticCall, 4, 1),
+            (Opcode::SelfDestruct, 0, 0),
+        ];
+
+        let revisions = REVISIONS.into_iter().filter(|revision| {
+            *revision >= Revision::EVMC_BERLIN && *revision <= Revision::EVMC_CANCUN
+        });
+        for revision in revisions {
+            let tx_context = ExecutionTxContext {
+                tx_origin: origin,
+                block_coinbase: coinbase,
+                ..Default::default()
+            };
+            let gas_used = |op: Opcode, before, after, addr: &Address| {
+                let code = [zeros(before), push_addr(addr), zeros(after), vec![op as u8]].concat();
+                let mut host = InMemoryHost::new(revision, tx_context.clone());
+                let result = execute(revision, &code, 0, &mut host);
+                assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+                1_000_000 - result.gas_left()
+            };
+            let cases = [
+                (origin, true),
+                (recipient, true),
+                (coinbase, revision >= Revision::EVMC_SHANGHAI),
+                (precompile(0x01), true),
+                (precompile(0x09), true),
+                (precompile(0x0a), revision >= Revision::EVMC_CANCUN),
+                (precompile(0x0b), false),
+            ];
+            for (op, before, after) in ops {
+                let cold_gas_used = gas_used(op, before, after, &cold);
+                let cold_cost = if op == Opcode::SelfDestruct {
+                    2600
+                } else {
+                    2500
+                };
+                for (addr, warm) in cases {
+                    let expected = if warm {
+                        cold_gas_used - cold_cost
+                    } else {
+                        cold_gas_used
+                    };
+                    assert_eq!(
+                        gas_used(op, before, after, &addr),
+                        expected,
+                        "{op:?} {revision:?} {addr:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn opcodes_are_undefined_before_their_revision() {
+        let cases = [
+            (Opcode::DelegateCall, Revision::EVMC_HOMESTEAD),
+            (Opcode::ReturnDataSize, Revision::EVMC_BYZANTIUM),
+            (Opcode::ReturnDataCopy, Revision::EVMC_BYZANTIUM),
+            (Opcode::StaticCall, Revision::EVMC_BYZANTIUM),
+            (Opcode::Revert, Revision::EVMC_BYZANTIUM),
+            (Opcode::Shl, Revision::EVMC_CONSTANTINOPLE),
+            (Opcode::Shr, Revision::EVMC_CONSTANTINOPLE),
+            (Opcode::Sar, Revision::EVMC_CONSTANTINOPLE),
+            (Opcode::ExtCodeHash, Revision::EVMC_CONSTANTINOPLE),
+            (Opcode::Create2, Revision::EVMC_CONSTANTINOPLE),
+            (Opcode::ChainId, Revision::EVMC_ISTANBUL),
+        ];
+        for (op, min_revision) in cases {
+            let code = [[Opcode::Push1 as u8, 0x00].repeat(7), vec![op as u8]].concat();
+            for revision in REVISIONS
+                .into_iter()
+                .take_while(|r| *r <= Revision::EVMC_CANCUN)
+            {
+                let mut host = InMemoryHost::new(revision, Default::default());
+                let undefined = matches!(
+                    try_execute(revision, &code, 0, &mut host),
+                    Err(ExecutionError {
+                        reason: FailureReason::UndefinedInstruction,
+                        ..
+                    })
+                );
+                assert_eq!(undefined, revision < min_revision, "{op:?} {revision:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn gas_costs_depend_on_revision() {
+        let gas_used = |revision, code: &[u8]| {
+            let mut host = InMemoryHost::new(revision, Default::default());
+            let result = execute(revision, code, 0, &mut host);
+            assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+            1_000_000 - result.gas_left()
+        };
+        let push0 = [Opcode::Push1 as u8, 0x00];
+        let unary = |op: Opcode| [&push0[..], &[op as u8]].concat();
+        let exp = [
+            Opcode::Push1 as u8,
+            0x01,
+            Opcode::Push1 as u8,
+            0x02,
+            Opcode::Exp as u8,
+        ];
+        let sstore = |value: u8| {
+            [
+                Opcode::Push1 as u8,
+                value,
+                push0[0],
+                push0[1],
+                Opcode::SStore as u8,
+            ]
+        };
+        // (revision, BALANCE, EXTCODESIZE, SLOAD, EXP, SSTORE of 0 to an empty slot)
+        let cases = [
+            (Revision::EVMC_FRONTIER, 20, 20, 50, 20, 5000),
+            (Revision::EVMC_TANGERINE_WHISTLE, 400, 700, 200, 20, 5000),
+            (Revision::EVMC_SPURIOUS_DRAGON, 400, 700, 200, 60, 5000),
+            (Revision::EVMC_CONSTANTINOPLE, 400, 700, 200, 60, 200),
+            (Revision::EVMC_PETERSBURG, 400, 700, 200, 60, 5000),
+            (Revision::EVMC_ISTANBUL, 700, 700, 800, 60, 800),
+        ];
+        for (revision, balance, extcodesize, sload, exp_cost, sstore_cost) in cases {
+            assert_eq!(gas_used(revision, &unary(Opcode::Balance)), 3 + balance);
+            assert_eq!(
+                gas_used(revision, &unary(Opcode::ExtCodeSize)),
+                3 + extcodesize
+            );
+            assert_eq!(gas_used(revision, &unary(Opcode::SLoad)), 3 + sload);
+            assert_eq!(gas_used(revision, &exp), 3 + 3 + exp_cost);
+            assert_eq!(gas_used(revision, &sstore(0)), 3 + 3 + sstore_cost);
+            assert_eq!(gas_used(revision, &sstore(1)), 3 + 3 + 20000);
+        }
+    }
+
+    #[test]
+    fn call_gas_must_be_available_before_tangerine_whistle() {
+        let code = [
+            [Opcode::Push1 as u8, 0x00].repeat(6),
+            vec![
+                Opcode::Push4 as u8,
+                0xff,
+                0xff,
+                0xff,
+                0xff,
+                Opcode::Call as u8,
+            ],
+        ]
+        .concat();
+        for revision in [Revision::EVMC_FRONTIER, Revision::EVMC_HOMESTEAD] {
+            let mut host = InMemoryHost::new(revision, Default::default());
+            let err = try_execute(revision, &code, 0, &mut host).unwrap_err();
+            assert_eq!(err.reason, FailureReason::OutOfGas);
+        }
+        let revision = Revision::EVMC_TANGERINE_WHISTLE;
+        let mut host = InMemoryHost::new(revision, Default::default());
+        let result = execute(revision, &code, 0, &mut host);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+    }
+
+    /// Returns a host in which `delegating` has a delegation designator to `delegate`, whose code
+    /// returns 1 and its own address.
+    fn host_with_delegation(
+        revision: Revision,
+        tx_context: ExecutionTxContext,
+        delegating: Address,
+        delegate: Address,
+    ) -> InMemoryHost {
+        let mut host = InMemoryHost::new(revision, tx_context);
+        host.set_account(
+            delegating,
+            Account {
+                code: [&[0xef, 0x01, 0x00][..], &delegate.bytes].concat(),
+                ..Default::default()
+            },
+        );
+        host.set_account(
+            delegate,
+            Account {
+                code: vec![
+                    Opcode::Push1 as u8,
+                    0x01,
+                    Opcode::Push1 as u8,
+                    0x00,
+                    Opcode::MStore as u8,
+                    Opcode::Address as u8,
+                    Opcode::Push1 as u8,
+                    0x20,
+                    Opcode::MStore as u8,
+                    Opcode::Push1 as u8,
+                    0x40,
+                    Opcode::Push1 as u8,
+                    0x00,
+                    Opcode::Return as u8,
+                ],
+                ..Default::default()
+            },
+        );
+        host
+    }
+
+    #[test]
+    fn extcode_ops_return_delegation_designator() {
+        let delegating = Address { bytes: [0xaa; 20] };
+        let delegate = Address { bytes: [0xbb; 20] };
+        let push_addr = [&[Opcode::Push20 as u8][..], &delegating.bytes].concat();
+        let code = [
+            &push_addr[..],
+            &[
+                Opcode::ExtCodeSize as u8,
+                Opcode::Push1 as u8,
+                0x00,
+                Opcode::MStore as u8,
+            ],
+            &push_addr,
+            &[
+                Opcode::ExtCodeHash as u8,
+                Opcode::Push1 as u8,
+                0x20,
+                Opcode::MStore as u8,
+            ],
+            &[
+                Opcode::Push1 as u8,
+                23,
+                Opcode::Push1 as u8,
+                0x00,
+                Opcode::Push1 as u8,
+                0x40,
+            ],
+            &push_addr,
+            &[Opcode::ExtCodeCopy as u8],
+            &[
+                Opcode::Push1 as u8,
+                0x60,
+                Opcode::Push1 as u8,
+                0x00,
+                Opcode::Return as u8,
+            ],
+        ]
+        .concat();
+        let designator = [&[0xef, 0x01, 0x00][..], &delegate.bytes].concat();
+
+        let revision = Revision::EVMC_PRAGUE;
+        let mut host = host_with_delegation(revision, Default::default(), delegating, delegate);
+        let result = execute(revision, &code, 0, &mut host);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        let output = result.output().unwrap();
+        assert_eq!(output[..32], Uint256::from(u256::from(23u8)).bytes);
+        assert_eq!(output[32..64], Uint256::from(keccak256(&designator)).bytes);
+        assert_eq!(output[64..87], designator);
+    }
+
+    #[test]
+    fn calls_execute_code_of_delegate() {
+        let recipient = Address::default();
+        let delegating = Address { bytes: [0xaa; 20] };
+        let delegate = Address { bytes: [0xbb; 20] };
+        let zeros = |n| [Opcode::Push1 as u8, 0x00].repeat(n);
+        let ret = [Opcode::Push1 as u8, 0x40, Opcode::Push1 as u8, 0x00];
+        let push_addr = [&[Opcode::Push20 as u8][..], &delegating.bytes].concat();
+        let gas = [Opcode::Push2 as u8, 0xff, 0xff];
+        let output = [
+            Opcode::Push1 as u8,
+            0x40,
+            Opcode::Push1 as u8,
+            0x00,
+            Opcode::Return as u8,
+        ];
+        // (opcode, arguments before the address, address in the context of the delegate)
+        let cases = [
+            (Opcode::Call, [&ret[..], &zeros(3)].concat(), delegating),
+            (Opcode::CallCode, [&ret[..], &zeros(3)].concat(), recipient),
+            (
+                Opcode::DelegateCall,
+                [&ret[..], &zeros(2)].concat(),
+                recipient,
+            ),
+            (
+                Opcode::StaticCall,
+                [&ret[..], &zeros(2)].concat(),
+                delegating,
+            ),
+        ];
+        for (op, args, context) in cases {
+            let code = [
+                &args[..],
+                &push_addr,
+                &gas,
+                &[op as u8, Opcode::Pop as u8],
+                &output,
+            ]
+            .concat();
+            for revision in [Revision::EVMC_CANCUN, Revision::EVMC_PRAGUE] {
+                let gas_used = |tx_context: ExecutionTxContext| {
+                    let mut host = host_with_delegation(revision, tx_context, delegating, delegate);
+                    let result = execute(revision, &code, 0, &mut host);
+                    assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+                    (
+                        1_000_000 - result.gas_left(),
+                        result.output().unwrap().to_vec(),
+                    )
+                };
+                let (cold_gas_used, output) = gas_used(Default::default());
+                let (warm_gas_used, _) = gas_used(ExecutionTxContext {
+                    tx_origin: delegate,
+                    ..Default::default()
+                });
+                if revision >= Revision::EVMC_PRAGUE {
+                    assert_eq!(output[31], 1, "{op:?}");
+                    assert_eq!(output[44..64], context.bytes, "{op:?}");
+                    assert_eq!(cold_gas_used - warm_gas_used, 2500, "{op:?}");
+                } else {
+                    // The designator is executed as code and fails at 0xef.
+                    assert_eq!(output, [0; 64], "{op:?}");
+                    assert_eq!(cold_gas_used, warm_gas_used, "{op:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn eof_containers_are_validated_from_osaka() {
+        let code = [0xef, 0x00, 0x02];
+        let mut host = InMemoryHost::new(Revision::EVMC_OSAKA, ExecutionTxContext::default());
+        let error = try_execute(Revision::EVMC_OSAKA, &code, 0, &mut host).unwrap_err();
+        assert_eq!(
+            error.reason,
+            FailureReason::InvalidContainer(EofError::InvalidHeader)
+        );
+        assert_eq!(
+            error.status_code(),
+            StatusCode::EVMC_CONTRACT_VALIDATION_FAILURE
+        );
+
+        // Before Osaka the code is legacy code which starts with an invalid opcode.
+        let mut host = InMemoryHost::new(Revision::EVMC_PRAGUE, ExecutionTxContext::default());
+        let error = try_execute(Revision::EVMC_PRAGUE, &code, 0, &mut host).unwrap_err();
+        assert_eq!(error.reason, FailureReason::InvalidInstruction);
+    }
+
+    #[test]
+    fn eof_code_executes_functions_jumps_and_stack_ops() {
+        let main = [
+            Opcode::Push1 as u8,
+            0x01,
+            Opcode::RJumpI as u8,
+            0x00,
+            0x01,
+            Opcode::Invalid as u8,
+            Opcode::Push1 as u8,
+            0x01,
+            Opcode::RJumpV as u8,
+            0x01, // two entries
+            0x00,
+            0x00,
+            0x00,
+            0x01,
+            Opcode::Invalid as u8,
+            Opcode::CallF as u8,
+            0x00,
+            0x01,
+            Opcode::Push1 as u8,
+            0x07,
+            Opcode::Push1 as u8,
+            0x09,
+            Opcode::DupN as u8,
+            0x02,
+            Opcode::SwapN as u8,
+            0x00,
+            Opcode::Exchange as u8,
+            0x00,
+            Opcode::Push0 as u8,
+            Opcode::MStore as u8,
+            Opcode::Push1 as u8,
+            0x20,
+            Opcode::MStore as u8,
+            Opcode::Push1 as u8,
+            0x40,
+            Opcode::MStore as u8,
+            Opcode::Push1 as u8,
+            0x60,
+            Opcode::MStore as u8,
+            Opcode::Push1 as u8,
+            0x80,
+            Opcode::Push0 as u8,
+            Opcode::Return as u8,
+        ];
+        let function = [Opcode::DataLoadN as u8, 0x00, 0x00, Opcode::RetF as u8];
+        let data: Vec<_> = (1..=32).collect();
+        let code = container(&[(0, 0x80, 5, &main), (0, 1, 1, &function)], &[], &data, 32);
+
+        let mut host = InMemoryHost::new(Revision::EVMC_OSAKA, ExecutionTxContext::default());
+        let result = execute(Revision::EVMC_OSAKA, &code, 0, &mut host);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        let word = |value: u8| {
+            let mut word = [0; 32];
+            word[31] = value;
+            word
+        };
+        assert_eq!(
+            *result.output().unwrap(),
+            [&word(9)[..], &word(7), &data, &data].concat()
+        );
+    }
+
+    #[test]
+    fn extcalls_push_status_and_fail_lightly() {
+        let callee = Address { bytes: [0xaa; 20] };
+        let push_callee = [&[Opcode::Push20 as u8][..], &callee.bytes].concat();
+        let code = [
+            &[
+                Opcode::Push0 as u8,
+                Opcode::Push0 as u8,
+                Opcode::Push0 as u8,
+            ][..],
+            &push_callee,
+            &[
+                Opcode::ExtCall as u8,
+                Opcode::Push0 as u8,
+                Opcode::MStore as u8,
+                Opcode::Push0 as u8,
+                Opcode::ReturnDataLoad as u8,
+                Opcode::Push1 as u8,
+                0x20,
+                Opcode::MStore as u8,
+                Opcode::Push0 as u8,
+                Opcode::Push0 as u8,
+            ],
+            &push_callee,
+            &[
+                // fails lightly because the callee is not an EOF contract
+                Opcode::ExtDelegateCall as u8,
+                Opcode::Push1 as u8,
+                0x40,
+                Opcode::MStore as u8,
+                Opcode::Push1 as u8,
+                0x60,
+                Opcode::Push0 as u8,
+                Opcode::Return as u8,
+            ],
+        ]
+        .concat();
+        let code = container(&[(0, 0x80, 4, &code)], &[], &[], 0);
+        let mut host = InMemoryHost::new(Revision::EVMC_OSAKA, ExecutionTxContext::default());
+        host.set_account(
+            callee,
+            Account {
+                code: vec![
+                    Opcode::Push1 as u8,
+                    0x2a,
+                    Opcode::Push0 as u8,
+                    Opcode::MStore as u8,
+                    Opcode::Push1 as u8,
+                    0x20,
+                    Opcode::Push0 as u8,
+                    Opcode::Revert as u8,
+                ],
+                ..Default::default()
+            },
+        );
+
+        let result = execute(Revision::EVMC_OSAKA, &code, 0, &mut host);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        let output = result.output().unwrap();
+        assert_eq!(output[31], 1);
+        assert_eq!(output[63], 0x2a);
+        assert_eq!(output[95], 1);
+
+        let code = [
+            &[
+                Opcode::Push0 as u8,
+                Opcode::Push0 as u8,
+                Opcode::Push0 as u8,
+            ][..],
+            &[Opcode::Push32 as u8],
+            &[0xff; 32],
+            &[Opcode::ExtCall as u8, Opcode::Stop as u8],
+        ]
+        .concat();
+        let code = container(&[(0, 0x80, 4, &code)], &[], &[], 0);
+        let error = try_execute(Revision::EVMC_OSAKA, &code, 0, &mut host).unwrap_err();
+        assert_eq!(error.reason, FailureReason::AddressOutOfRange(u256::MAX));
+    }
+
+    #[test]
+    fn eofcreate_deploys_runtime_container_with_aux_data() {
+        let runtime = container(&[(0, 0x80, 0, &[Opcode::Stop as u8])], &[], &[], 32);
+        let init_code = [
+            Opcode::Push0 as u8,
+            Opcode::CallDataLoad as u8,
+            Opcode::Push0 as u8,
+            Opcode::MStore as u8,
+            Opcode::Push1 as u8,
+            0x20,
+            Opcode::Push0 as u8,
+            Opcode::ReturnContract as u8,
+            0x00,
+        ];
+        let init_container = container(&[(0, 0x80, 2, &init_code)], &[&runtime], &[], 0);
+        let factory = [
+            Opcode::Push1 as u8,
+            0x2a,
+            Opcode::Push0 as u8,
+            Opcode::MStore as u8,
+            Opcode::Push1 as u8,
+            0x20,
+            Opcode::Push0 as u8,
+            Opcode::Push0 as u8,
+            Opcode::Push0 as u8,
+            Opcode::EofCreate as u8,
+            0x00,
+            Opcode::Push0 as u8,
+            Opcode::MStore as u8,
+            Opcode::Push1 as u8,
+            0x20,
+            Opcode::Push0 as u8,
+            Opcode::Return as u8,
+        ];
+        let code = container(&[(0, 0x80, 4, &factory)], &[&init_container], &[], 0);
+
+        let mut host = InMemoryHost::new(Revision::EVMC_OSAKA, ExecutionTxContext::default());
+        let result = execute(Revision::EVMC_OSAKA, &code, 0, &mut host);
+        assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+        let addr = Address {
+            bytes: result.output().unwrap()[12..].try_into().unwrap(),
+        };
+        let mut aux_data = [0; 32];
+        aux_data[31] = 0x2a;
+        assert_eq!(
+            host.account(&addr).unwrap().code,
+            container(&[(0, 0x80, 0, &[Opcode::Stop as u8])], &[], &aux_data, 32)
+        );
+
+        // Legacy code sees only the magic of the deployed EOF contract.
+        let push_addr = [&[Opcode::Push20 as u8][..], &addr.bytes].concat();
+        let code = [
+            &push_addr[..],
+            &[
+                Opcode::ExtCodeSize as u8,
+                Opcode::Push0 as u8,
+                Opcode::MStore as u8,
+            ],
+            &push_addr,
+            &[
+                Opcode::ExtCodeHash as u8,
+                Opcode::Push1 as u8,
+                0x20,
+                Opcode::MStore as u8,
+                Opcode::Push1 as u8,
+                0x40,
+                Opcode::Push0 as u8,
+                Opcode::Return as u8,
+            ],
+        ]
+        .concat();
+        let result = execute(Revision::EVMC_OSAKA, &code, 0, &mut host);
+        let output = result.output().unwrap();
+        assert_eq!(output[31], 2);
+        assert_eq!(output[32..], Uint256::from(keccak256(&[0xef, 0x00])).bytes);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{interpreter::code_analysis::Instruction, types::Opcode};
+
+/// The valid jump destinations of a piece of code as a bitmap with one bit per code byte.
+#[derive(Debug)]
+pub struct JumpDests(Box<[u64]>);
+
+impl JumpDests {
+    /// `pcs` are the pcs of `instructions`, the last one is the end of the code.
+    pub fn new(instructions: &[Instruction], pcs: &[u32]) -> Self {
+        let code_len = pcs.last().copied().unwrap_or_default() as usize;
+        let mut bits = vec![0; code_len.div_ceil(64)];
+        for (instruction, pc) in instructions.iter().zip(pcs) {
+            if instruction.op() == Some(Opcode::JumpDest) {
+                let pc = *pc as usize;
+                bits[pc / 64] |= 1 << (pc % 64);
+            }
+        }
+        Self(bits.into_boxed_slice())
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, pc: usize) -> bool {
+        self.0
+            .get(pc / 64)
+            .is_some_and(|bits| bits & (1 << (pc % 64)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interpreter::CodeAnalysis, types::Opcode};
+
+    #[test]
+    fn jump_dests_skip_push_data() {
+        let mut code = vec![
+            Opcode::JumpDest as u8,
+            Opcode::Push2 as u8,
+            Opcode::JumpDest as u8,
+            Opcode::JumpDest as u8,
+        ];
+        code.resize(100, Opcode::Add as u8);
+        code.push(Opcode::JumpDest as u8);
+        let analysis = CodeAnalysis::new(&code);
+        let jump_dests = analysis.jump_dests();
+
+        let valid: Vec<_> = (0..200).filter(|pc| jump_dests.contains(*pc)).collect();
+        assert_eq!(valid, [0, 100]);
+    }
+}
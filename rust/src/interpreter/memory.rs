@@ -1,39 +1,72 @@
-use std::{cmp::max, iter};
-
-use evmc_vm::{StatusCode, StepStatusCode};
+use std::{cmp::max, mem};
 
 use crate::{
-    interpreter::{consume_copy_cost, consume_gas, word_size, OUT_OF_GAS_ERR},
+    interpreter::{
+        consume_copy_cost, consume_gas, error::FailureReason, word_size, OUT_OF_GAS_ERR,
+    },
     types::u256,
+    utils::Pool,
 };
 
-#[derive(Debug)]
+thread_local! {
+    // One memory per call depth for the depths most transactions reach. Deeper calls and larger
+    // buffers are freed so that a single execution with a lot of memory does not keep it
+    // allocated.
+    static POOL: Pool<u8> = const { Pool::new(32, 1 << 16) };
+}
+
+/// Converts the length of an access of memory at `offset`. Lengths which do not fit into 64 bits
+/// can never be paid for.
+#[inline(always)]
+pub fn access_len(offset: u256, len: u256) -> Result<u64, FailureReason> {
+    let (len_u64, len_overflow) = len.into_u64_with_overflow();
+    if len_overflow {
+        return Err(FailureReason::MemoryOverflow { offset, len });
+    }
+    Ok(len_u64)
+}
+
+/// The buffer of the memory is returned to a per thread pool when the memory is dropped.
+#[derive(Debug, Clone)]
 pub struct Memory(Vec<u8>);
 
+impl Drop for Memory {
+    fn drop(&mut self) {
+        let buffer = mem::take(&mut self.0);
+        // The pool may already be destroyed if this happens during thread shutdown.
+        let _ = POOL.try_with(|pool| pool.recycle(buffer));
+    }
+}
+
 impl Memory {
     pub fn new(memory: Vec<u8>) -> Self {
         Self(memory)
     }
 
-    pub fn into_inner(self) -> Vec<u8> {
-        self.0
+    /// Returns an empty memory which reuses a buffer from the pool if there is one.
+    pub fn pooled() -> Self {
+        Self(POOL.with(|pool| pool.take(0)))
+    }
+
+    pub fn into_inner(mut self) -> Vec<u8> {
+        mem::take(&mut self.0)
     }
 
     pub fn len(&self) -> u64 {
         self.0.len() as u64
     }
 
-    fn expand(
-        &mut self,
-        new_len_bytes: u64,
-        gas_left: &mut u64,
-    ) -> Result<(), (StepStatusCode, StatusCode)> {
+    /// Shrinks the memory to `len` bytes. This is used to undo an expansion by a failed opcode.
+    pub fn truncate(&mut self, len: u64) {
+        self.0.truncate(len as usize);
+    }
+
+    fn expand(&mut self, new_len_bytes: u64, gas_left: &mut u64) -> Result<(), FailureReason> {
         let current_len = self.0.len() as u64;
         let new_len = word_size(new_len_bytes)? * 32; // word_size just did a division by 32 so * will not overflow
         if new_len > current_len {
             self.consume_expansion_cost(gas_left, new_len)?;
-            self.0
-                .extend(iter::repeat(0).take((new_len - current_len) as usize))
+            self.0.resize(new_len as usize, 0);
         }
         Ok(())
     }
@@ -42,8 +75,8 @@ impl Memory {
         &self,
         gas_left: &mut u64,
         new_len: u64,
-    ) -> Result<(), (StepStatusCode, StatusCode)> {
-        fn memory_cost(size: u64) -> Result<u64, (StepStatusCode, StatusCode)> {
+    ) -> Result<(), FailureReason> {
+        fn memory_cost(size: u64) -> Result<u64, FailureReason> {
             let word_size = word_size(size)?;
             let (pow2, pow2_overflow) = word_size.overflowing_pow(2);
             let (word_size_3, word_size_3_overflow) = word_size.overflowing_mul(3);
@@ -68,36 +101,31 @@ impl Memory {
         offset: u256,
         len: u64,
         gas_left: &mut u64,
-    ) -> Result<&mut [u8], (StepStatusCode, StatusCode)> {
+    ) -> Result<&mut [u8], FailureReason> {
         if len == 0 {
             return Ok(&mut []);
         }
-        let (offset, offset_overflow) = offset.into_u64_with_overflow();
-        let (end, end_overflow) = offset.overflowing_add(len);
+        let (offset_u64, offset_overflow) = offset.into_u64_with_overflow();
+        let (end, end_overflow) = offset_u64.overflowing_add(len);
         if offset_overflow || end_overflow {
-            OUT_OF_GAS_ERR?;
+            return Err(FailureReason::MemoryOverflow {
+                offset,
+                len: len.into(),
+            });
         }
         self.expand(end, gas_left)?;
 
-        Ok(&mut self.0[offset as usize..end as usize])
+        Ok(&mut self.0[offset_u64 as usize..end as usize])
     }
 
-    pub fn get_word(
-        &mut self,
-        offset: u256,
-        gas_left: &mut u64,
-    ) -> Result<u256, (StepStatusCode, StatusCode)> {
+    pub fn get_word(&mut self, offset: u256, gas_left: &mut u64) -> Result<u256, FailureReason> {
         let slice = self.get_slice(offset, 32u8.into(), gas_left)?;
         let mut bytes = [0; 32];
         bytes.copy_from_slice(slice);
         Ok(bytes.into())
     }
 
-    pub fn get_byte(
-        &mut self,
-        offset: u256,
-        gas_left: &mut u64,
-    ) -> Result<&mut u8, (StepStatusCode, StatusCode)> {
+    pub fn get_byte(&mut self, offset: u256, gas_left: &mut u64) -> Result<&mut u8, FailureReason> {
         self.get_slice(offset, 1u8.into(), gas_left)
             .map(|slice| &mut slice[0])
     }
@@ -108,19 +136,22 @@ impl Memory {
         dest_offset: u256,
         len: u256,
         gas_left: &mut u64,
-    ) -> Result<(), (StepStatusCode, StatusCode)> {
-        let (src_offset, src_overflow) = src_offset.into_u64_with_overflow();
-        let (dest_offset, dest_overflow) = dest_offset.into_u64_with_overflow();
-        let (len, len_overflow) = len.into_u64_with_overflow();
-        let (end, end_overflow) = max(src_offset, dest_offset).overflowing_add(len);
+    ) -> Result<(), FailureReason> {
+        let (src_offset_u64, src_overflow) = src_offset.into_u64_with_overflow();
+        let (dest_offset_u64, dest_overflow) = dest_offset.into_u64_with_overflow();
+        let (len_u64, len_overflow) = len.into_u64_with_overflow();
+        let (end, end_overflow) = max(src_offset_u64, dest_offset_u64).overflowing_add(len_u64);
         if src_overflow || dest_overflow || len_overflow || end_overflow {
-            return OUT_OF_GAS_ERR;
+            return Err(FailureReason::MemoryOverflow {
+                offset: max(src_offset, dest_offset),
+                len,
+            });
         }
-        consume_copy_cost(gas_left, len)?;
+        consume_copy_cost(gas_left, len_u64)?;
         self.expand(end, gas_left)?;
-        let src_offset = src_offset as usize;
-        let dest_offset = dest_offset as usize;
-        let len = len as usize;
+        let src_offset = src_offset_u64 as usize;
+        let dest_offset = dest_offset_u64 as usize;
+        let len = len_u64 as usize;
         self.0
             .copy_within(src_offset..src_offset + len, dest_offset); // + does not overflow
         Ok(())
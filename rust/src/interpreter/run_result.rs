@@ -1,6 +1,6 @@
-use std::mem;
+use std::{borrow::Cow, mem};
 
-use evmc_vm::{Revision, StatusCode, StepResult, StepStatusCode, Uint256};
+use evmc_vm::{ExecutionResult, Revision, StatusCode, StepResult, StepStatusCode, Uint256};
 
 use crate::{
     interpreter::{CodeState, Memory, Stack},
@@ -18,7 +18,7 @@ pub struct RunResult<'a> {
     output: Option<Vec<u8>>,
     stack: Stack,
     memory: Memory,
-    last_call_return_data: Option<Vec<u8>>,
+    last_call_return_data: Option<Cow<'a, [u8]>>,
 }
 
 impl<'a> RunResult<'a> {
@@ -33,7 +33,7 @@ impl<'a> RunResult<'a> {
         output: Option<Vec<u8>>,
         stack: Stack,
         memory: Memory,
-        last_call_return_data: Option<Vec<u8>>,
+        last_call_return_data: Option<Cow<'a, [u8]>>,
     ) -> Self {
         Self {
             step_status_code,
@@ -69,7 +69,20 @@ impl<'a> From<RunResult<'a>> for StepResult {
             value.output,
             stack,
             value.memory.into_inner(),
-            value.last_call_return_data,
+            value.last_call_return_data.map(Cow::into_owned),
+        )
+    }
+}
+
+impl<'a> From<RunResult<'a>> for ExecutionResult {
+    fn from(value: RunResult) -> Self {
+        // Stack and memory are not needed anymore and are dropped here, which returns their
+        // buffers to the pool.
+        ExecutionResult::new(
+            value.status_code,
+            value.gas_left as i64,
+            value.gas_refund,
+            value.output.as_deref(),
         )
     }
 }
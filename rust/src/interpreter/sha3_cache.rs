@@ -0,0 +1,81 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
+use sha3::{Digest, Keccak256};
+
+use crate::{types::u256, utils::LruCache};
+
+const CACHE_SIZE: usize = 1 << 10;
+
+/// Caches the keccak hashes of 32 and 64 byte inputs. These are the sizes used by Solidity to
+/// compute the storage slots of mappings so the same inputs are hashed over and over again.
+#[derive(Debug, Default)]
+pub struct Sha3Cache {
+    cache_32: LruCache<[u8; 32], u256, CACHE_SIZE, BuildHasherDefault<KeyHasher>>,
+    cache_64: LruCache<[u8; 64], u256, CACHE_SIZE, BuildHasherDefault<KeyHasher>>,
+}
+
+impl Sha3Cache {
+    pub fn hash(&self, data: &[u8]) -> u256 {
+        if let Ok(key) = <[u8; 32]>::try_from(data) {
+            self.cache_32.get_or_insert_with(key, || keccak256(data))
+        } else if let Ok(key) = <[u8; 64]>::try_from(data) {
+            self.cache_64.get_or_insert_with(key, || keccak256(data))
+        } else {
+            keccak256(data)
+        }
+    }
+}
+
+/// A hasher which folds the input in 8 byte words with a multiply and rotate, like FxHash. This is
+/// much cheaper than the default SipHash and good enough for keys like storage slots and addresses,
+/// because the cache can not be flooded with colliding keys without paying for the SHA3s.
+#[derive(Debug, Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.write_u64(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for byte in chunks.remainder() {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    fn write_usize(&mut self, word: usize) {
+        self.write_u64(word as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub fn keccak256(data: &[u8]) -> u256 {
+    let mut bytes = [0; 32];
+    Keccak256::new()
+        .chain_update(data)
+        .finalize_into((&mut bytes).into());
+    bytes.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::sha3_cache::{keccak256, Sha3Cache};
+
+    #[test]
+    fn sha3_cache_returns_keccak_hash() {
+        let cache = Sha3Cache::default();
+        for len in [0, 1, 32, 64, 65] {
+            let data: Vec<_> = (0..len).map(|i| i as u8).collect();
+            assert_eq!(cache.hash(&data), keccak256(&data));
+            // cached
+            assert_eq!(cache.hash(&data), keccak256(&data));
+        }
+    }
+}
@@ -1,26 +1,69 @@
-use evmc_vm::{StatusCode, StepStatusCode};
+use std::{cmp::min, mem};
 
-use crate::types::u256;
+use crate::{interpreter::error::FailureReason, types::u256, utils::Pool};
 
+pub const STACK_SIZE_LIMIT: usize = 1024;
+
+/// The maximum number of elements popped by an opcode (CALL).
+const MAX_POPPED: usize = 7;
+
+/// See [`Stack::top`].
+pub struct StackTop {
+    len: usize,
+    elements: [u256; MAX_POPPED],
+}
+
+thread_local! {
+    // One stack per call depth for the depths most transactions reach. Deeper calls allocate, so
+    // that a single deep execution does not keep a full stack per depth allocated.
+    static POOL: Pool<u256> = const { Pool::new(32, STACK_SIZE_LIMIT) };
+}
+
+/// The buffer of the stack is returned to a per thread pool when the stack is dropped.
 #[derive(Debug)]
 pub struct Stack(Vec<u256>);
 
+impl Drop for Stack {
+    fn drop(&mut self) {
+        let buffer = mem::take(&mut self.0);
+        // The pool may already be destroyed if this happens during thread shutdown.
+        let _ = POOL.try_with(|pool| pool.recycle(buffer));
+    }
+}
+
 impl Stack {
     pub fn new(inner: Vec<u256>) -> Self {
         Self(inner)
     }
 
-    pub fn into_inner(self) -> Vec<u256> {
-        self.0
+    /// Returns a stack which takes over `stack` and can grow by `max_growth` elements without
+    /// reallocating. The growth is capped at the free space up to the stack limit.
+    pub fn with_growth(mut stack: Vec<u256>, max_growth: usize) -> Self {
+        let growth = min(max_growth, STACK_SIZE_LIMIT.saturating_sub(stack.len()));
+        stack.reserve_exact(growth);
+        Self(stack)
+    }
+
+    /// Returns an empty stack which reuses a buffer from the pool if there is one.
+    pub fn pooled() -> Self {
+        Self(POOL.with(|pool| pool.take(STACK_SIZE_LIMIT)))
+    }
+
+    pub fn into_inner(mut self) -> Vec<u256> {
+        mem::take(&mut self.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
     }
 
-    pub fn push(&mut self, value: impl Into<u256>) -> Result<(), (StepStatusCode, StatusCode)> {
+    pub fn push(&mut self, value: impl Into<u256>) -> Result<(), FailureReason> {
         self.check_overflow_on_push()?;
         self.0.push(value.into());
         Ok(())
     }
 
-    pub fn swap_with_top(&mut self, nth: usize) -> Result<(), (StepStatusCode, StatusCode)> {
+    pub fn swap_with_top(&mut self, nth: usize) -> Result<(), FailureReason> {
         self.check_underflow(nth + 1)?;
 
         let len = self.0.len();
@@ -28,7 +71,16 @@ impl Stack {
         Ok(())
     }
 
-    pub fn pop<const N: usize>(&mut self) -> Result<[u256; N], (StepStatusCode, StatusCode)> {
+    /// Swaps the elements `n` and `n + m` below the top of the stack.
+    pub fn exchange(&mut self, n: usize, m: usize) -> Result<(), FailureReason> {
+        self.check_underflow(n + m + 1)?;
+
+        let len = self.0.len();
+        self.0.swap(len - 1 - n, len - 1 - n - m);
+        Ok(())
+    }
+
+    pub fn pop<const N: usize>(&mut self) -> Result<[u256; N], FailureReason> {
         self.check_underflow(N)?;
 
         let mut array = [u256::ZERO; N];
@@ -38,30 +90,132 @@ impl Stack {
         Ok(array)
     }
 
-    pub fn nth(&self, nth: usize) -> Result<u256, (StepStatusCode, StatusCode)> {
+    pub fn nth(&self, nth: usize) -> Result<u256, FailureReason> {
         self.check_underflow(nth)?;
         Ok(self.0[self.0.len() - nth])
     }
 
+    /// Checks that at least `min_len` elements are on the stack and that `growth` elements can be
+    /// pushed.
+    #[inline(always)]
+    pub fn check_bounds(&self, min_len: usize, growth: usize) -> Result<(), FailureReason> {
+        self.check_underflow(min_len)?;
+        if self.0.len() + growth > STACK_SIZE_LIMIT {
+            return Err(FailureReason::StackOverflow);
+        }
+        Ok(())
+    }
+
+    /// Returns the length and the top elements of the stack, which are enough to undo a failed
+    /// opcode with [`Stack::restore`]. Opcodes pop at most 7 elements and do not modify deeper
+    /// elements or push elements before they can fail.
+    pub fn top(&self) -> StackTop {
+        let len = self.0.len();
+        let mut elements = [u256::ZERO; MAX_POPPED];
+        let top = &self.0[len.saturating_sub(MAX_POPPED)..];
+        elements[..top.len()].copy_from_slice(top);
+        StackTop { len, elements }
+    }
+
+    pub fn restore(&mut self, top: &StackTop) {
+        let start = top.len.saturating_sub(MAX_POPPED);
+        self.0.truncate(start);
+        self.0.extend_from_slice(&top.elements[..top.len - start]);
+    }
+
+    // The following functions do not check for stack underflows or overflows. They must only be
+    // used after the bounds were checked with check_bounds.
+
+    #[inline(always)]
+    pub fn push_unchecked(&mut self, value: impl Into<u256>) {
+        debug_assert!(self.0.len() < STACK_SIZE_LIMIT);
+        self.0.push(value.into());
+    }
+
     #[inline(always)]
-    fn check_overflow_on_push(&self) -> Result<(), (StepStatusCode, StatusCode)> {
-        if self.0.len() >= 1024 {
-            return Err((
-                StepStatusCode::EVMC_STEP_FAILED,
-                StatusCode::EVMC_STACK_OVERFLOW,
-            ));
+    pub fn pop_unchecked<const N: usize>(&mut self) -> [u256; N] {
+        let mut array = [u256::ZERO; N];
+        for element in &mut array {
+            *element = self.0.pop().unwrap();
+        }
+        array
+    }
+
+    #[inline(always)]
+    pub fn nth_unchecked(&self, nth: usize) -> u256 {
+        self.0[self.0.len() - nth]
+    }
+
+    #[inline(always)]
+    pub fn swap_with_top_unchecked(&mut self, nth: usize) {
+        let len = self.0.len();
+        self.0.swap(len - 1, len - 1 - nth);
+    }
+
+    #[inline(always)]
+    fn check_overflow_on_push(&self) -> Result<(), FailureReason> {
+        if self.0.len() >= STACK_SIZE_LIMIT {
+            return Err(FailureReason::StackOverflow);
         }
         Ok(())
     }
 
     #[inline(always)]
-    fn check_underflow(&self, nth: usize) -> Result<(), (StepStatusCode, StatusCode)> {
+    fn check_underflow(&self, nth: usize) -> Result<(), FailureReason> {
         if self.0.len() < nth {
-            return Err((
-                StepStatusCode::EVMC_STEP_FAILED,
-                StatusCode::EVMC_STACK_UNDERFLOW,
-            ));
+            return Err(FailureReason::StackUnderflow);
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interpreter::{stack::STACK_SIZE_LIMIT, FailureReason, Stack},
+        types::u256,
+    };
+
+    #[test]
+    fn stack_with_growth_reserves_growth() {
+        let elements = vec![u256::ZERO; 2];
+        let stack = Stack::with_growth(elements.clone(), 3);
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.0.capacity(), 5);
+
+        let stack = Stack::with_growth(elements, usize::MAX);
+        assert_eq!(stack.0.capacity(), STACK_SIZE_LIMIT);
+
+        let elements = vec![u256::ZERO; STACK_SIZE_LIMIT + 1];
+        let stack = Stack::with_growth(elements, 3);
+        assert_eq!(stack.0.capacity(), STACK_SIZE_LIMIT + 1);
+
+        // The buffer is taken over if it is already large enough.
+        let mut elements = Vec::with_capacity(8);
+        elements.extend([u256::ZERO; 2]);
+        let buffer = elements.as_ptr();
+        let stack = Stack::with_growth(elements, 3);
+        assert_eq!(stack.0.as_ptr(), buffer);
+    }
+
+    #[test]
+    fn stack_restore_undoes_pops() {
+        let elements: Vec<_> = (0..10u8).map(u256::from).collect();
+        for len in [0, 3, 10] {
+            let mut stack = Stack::new(elements[..len].to_vec());
+            let top = stack.top();
+            stack.0.truncate(len.saturating_sub(7));
+            stack.restore(&top);
+            assert_eq!(stack.0, elements[..len]);
+        }
+    }
+
+    #[test]
+    fn stack_exchange_swaps_elements_below_top() {
+        let elements: Vec<_> = (0..4u8).map(u256::from).collect();
+        let mut stack = Stack::new(elements);
+        stack.exchange(1, 2).unwrap();
+        assert_eq!(stack.0, [2u8.into(), 1u8.into(), u256::ZERO, 3u8.into()]);
+        assert_eq!(stack.exchange(1, 3), Err(FailureReason::StackUnderflow));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    interpreter::{
+        code_analysis::{push_len, Instruction},
+        error::FailureReason,
+        stack::STACK_SIZE_LIMIT,
+        CodeState, Stack,
+    },
+    types::{u256, Opcode},
+};
+
+/// A sequence of opcodes which is executed by a single handler.
+///
+/// Super instructions are only an optimization. A super instruction is attached to the first
+/// instruction of the sequence while all following instructions stay unchanged, so jumping into
+/// the middle of a sequence and single stepping still work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuperInstruction {
+    /// `PUSH1 x; JUMP` or `PUSH2 x; JUMP`
+    PushJump { len: u8 },
+    /// `PUSH x; PUSH y; SWAP1`
+    PushPushSwap1 { len1: u8, len2: u8 },
+    /// `DUP2; ADD`
+    Dup2Add,
+    /// `ISZERO; PUSH2 x; JUMPI`
+    IsZeroPush2JumpI,
+}
+
+impl SuperInstruction {
+    /// Returns the super instruction which starts at the instruction at `index`, if there is one.
+    pub fn find(instructions: &[Instruction], index: usize) -> Option<Self> {
+        let op_at = |index: usize| instructions.get(index).and_then(Instruction::op);
+
+        let first = op_at(index)?;
+        let second = op_at(index + 1)?;
+        match (first, second) {
+            (Opcode::Push1 | Opcode::Push2, Opcode::Jump) => Some(Self::PushJump {
+                len: push_len(first)? as u8,
+            }),
+            (Opcode::Dup2, Opcode::Add) => Some(Self::Dup2Add),
+            (Opcode::IsZero, Opcode::Push2) => {
+                (op_at(index + 2)? == Opcode::JumpI).then_some(Self::IsZeroPush2JumpI)
+            }
+            _ => {
+                let len1 = push_len(first)?;
+                let len2 = push_len(second)?;
+                (op_at(index + 2)? == Opcode::Swap1).then_some(Self::PushPushSwap1 {
+                    len1: len1 as u8,
+                    len2: len2 as u8,
+                })
+            }
+        }
+    }
+
+    /// Executes the whole sequence with a single gas and stack check. If these checks fail,
+    /// nothing is executed and `false` is returned so that the caller can execute the sequence
+    /// opcode by opcode, which yields the exact same error as without super instructions. If
+    /// `block_checked` is set, the checks were already done for the whole basic block.
+    #[inline(always)]
+    pub fn run(
+        self,
+        code_state: &mut CodeState,
+        stack: &mut Stack,
+        gas_left: &mut u64,
+        block_checked: bool,
+    ) -> Result<bool, FailureReason> {
+        if !block_checked {
+            let (gas, min_stack_len, max_stack_len) = match self {
+                Self::PushJump { .. } => (3 + 8, 0, STACK_SIZE_LIMIT - 1),
+                Self::PushPushSwap1 { .. } => (3 + 3 + 3, 0, STACK_SIZE_LIMIT - 2),
+                Self::Dup2Add => (3 + 3, 2, STACK_SIZE_LIMIT - 1),
+                Self::IsZeroPush2JumpI => (3 + 3 + 10, 1, STACK_SIZE_LIMIT - 1),
+            };
+            if *gas_left < gas || stack.len() < min_stack_len || stack.len() > max_stack_len {
+                return Ok(false);
+            }
+            *gas_left -= gas;
+        }
+
+        match self {
+            Self::PushJump { len } => {
+                let dest = code_state.get_push_data(len as usize);
+                code_state.try_jump(dest)?;
+            }
+            Self::PushPushSwap1 { len1, len2 } => {
+                let x = code_state.get_push_data(len1 as usize);
+                let y = code_state.get_push_data(len2 as usize);
+                stack.push_unchecked(y);
+                stack.push_unchecked(x);
+                code_state.next();
+            }
+            Self::Dup2Add => {
+                let [value1] = stack.pop_unchecked();
+                let value2 = stack.nth_unchecked(1);
+                stack.push_unchecked(value1 + value2);
+                code_state.next();
+                code_state.next();
+            }
+            Self::IsZeroPush2JumpI => {
+                let [value] = stack.pop_unchecked();
+                code_state.next();
+                let dest = code_state.get_push_data(2);
+                if value == u256::ZERO {
+                    code_state.try_jump(dest)?;
+                } else {
+                    code_state.next();
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interpreter::{super_instructions::SuperInstruction, CodeAnalysis, CodeState, Stack},
+        types::{u256, Opcode},
+    };
+
+    #[test]
+    fn super_instructions_are_found() {
+        let code = [
+            Opcode::Push1 as u8,
+            0x0a,
+            Opcode::Jump as u8,
+            Opcode::Push3 as u8,
+            0x01,
+            0x02,
+            0x03,
+            Opcode::Push1 as u8,
+            0x04,
+            Opcode::Swap1 as u8,
+            Opcode::Dup2 as u8,
+            Opcode::Add as u8,
+            Opcode::IsZero as u8,
+            Opcode::Push2 as u8,
+            0x00,
+            0x0a,
+            Opcode::JumpI as u8,
+        ];
+        let analysis = CodeAnalysis::new(&code);
+        let super_instructions: Vec<_> = analysis
+            .instructions()
+            .iter()
+            .map(|instruction| instruction.super_instruction())
+            .collect();
+        assert_eq!(
+            super_instructions[0],
+            Some(SuperInstruction::PushJump { len: 1 })
+        );
+        assert_eq!(
+            super_instructions[2],
+            Some(SuperInstruction::PushPushSwap1 { len1: 3, len2: 1 })
+        );
+        assert_eq!(super_instructions[5], Some(SuperInstruction::Dup2Add));
+        assert_eq!(
+            super_instructions[7],
+            Some(SuperInstruction::IsZeroPush2JumpI)
+        );
+        // Sequences which only match partially are not fused.
+        assert_eq!(super_instructions[3], None);
+        assert_eq!(super_instructions[8], None);
+    }
+
+    #[test]
+    fn super_instructions_are_not_found_in_truncated_code() {
+        let analysis = CodeAnalysis::new(&[Opcode::Push2 as u8, 0x01, Opcode::Jump as u8]);
+        assert_eq!(analysis.instructions()[0].super_instruction(), None);
+        let analysis = CodeAnalysis::new(&[Opcode::IsZero as u8, Opcode::Push2 as u8]);
+        assert_eq!(analysis.instructions()[0].super_instruction(), None);
+    }
+
+    #[test]
+    fn super_instruction_dup2_add() {
+        let code = [Opcode::Dup2 as u8, Opcode::Add as u8];
+        let analysis = CodeAnalysis::new(&code);
+        let mut code_state = CodeState::new(&code, &analysis, 0);
+        let mut stack = Stack::new(vec![1u8.into(), 2u8.into()]);
+        let mut gas_left = 6;
+        assert_eq!(
+            SuperInstruction::Dup2Add.run(&mut code_state, &mut stack, &mut gas_left, false),
+            Ok(true)
+        );
+        assert_eq!(stack.into_inner(), [u256::from(1u8), u256::from(3u8)]);
+        assert_eq!(code_state.pc(), 2);
+        assert_eq!(gas_left, 0);
+    }
+
+    #[test]
+    fn super_instruction_is_not_run_if_checks_fail() {
+        let code = [Opcode::Dup2 as u8, Opcode::Add as u8];
+        let analysis = CodeAnalysis::new(&code);
+        let mut code_state = CodeState::new(&code, &analysis, 0);
+
+        let mut stack = Stack::new(vec![1u8.into(), 2u8.into()]);
+        let mut gas_left = 5;
+        assert_eq!(
+            SuperInstruction::Dup2Add.run(&mut code_state, &mut stack, &mut gas_left, false),
+            Ok(false)
+        );
+        assert_eq!(gas_left, 5);
+
+        let mut stack = Stack::new(vec![1u8.into()]);
+        let mut gas_left = 6;
+        assert_eq!(
+            SuperInstruction::Dup2Add.run(&mut code_state, &mut stack, &mut gas_left, false),
+            Ok(false)
+        );
+        assert_eq!(code_state.pc(), 0);
+    }
+
+    #[test]
+    fn super_instruction_iszero_push2_jumpi() {
+        let code = [
+            Opcode::IsZero as u8,
+            Opcode::Push2 as u8,
+            0x00,
+            0x06,
+            Opcode::JumpI as u8,
+            Opcode::Stop as u8,
+            Opcode::JumpDest as u8,
+        ];
+        let analysis = CodeAnalysis::new(&code);
+
+        let mut code_state = CodeState::new(&code, &analysis, 0);
+        let mut stack = Stack::new(vec![u256::ZERO]);
+        let mut gas_left = 16;
+        assert_eq!(
+            SuperInstruction::IsZeroPush2JumpI.run(
+                &mut code_state,
+                &mut stack,
+                &mut gas_left,
+                false
+            ),
+            Ok(true)
+        );
+        assert_eq!(code_state.pc(), 6);
+        assert_eq!(stack.len(), 0);
+
+        let mut code_state = CodeState::new(&code, &analysis, 0);
+        let mut stack = Stack::new(vec![1u8.into()]);
+        let mut gas_left = 16;
+        assert_eq!(
+            SuperInstruction::IsZeroPush2JumpI.run(
+                &mut code_state,
+                &mut stack,
+                &mut gas_left,
+                false
+            ),
+            Ok(true)
+        );
+        assert_eq!(code_state.pc(), 5);
+    }
+}
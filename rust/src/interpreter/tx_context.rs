@@ -0,0 +1,54 @@
+use evmc_vm::ExecutionTxContext;
+
+use crate::host::Host;
+
+/// The transaction context does not change during a run, so it is fetched from the host at most
+/// once instead of on every access.
+#[derive(Default)]
+pub struct TxContextCache(Option<ExecutionTxContext>);
+
+impl TxContextCache {
+    #[inline(always)]
+    pub fn get(&mut self, host: &dyn Host) -> &ExecutionTxContext {
+        self.0.get_or_insert_with(|| host.get_tx_context())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ptr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use evmc_vm::{
+        ffi::{evmc_host_context, evmc_host_interface, evmc_tx_context},
+        ExecutionContext,
+    };
+
+    use crate::interpreter::tx_context::TxContextCache;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn get_tx_context(_context: *mut evmc_host_context) -> evmc_tx_context {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        evmc_tx_context {
+            block_number: 42,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tx_context_is_fetched_once() {
+        let host = evmc_host_interface {
+            get_tx_context: Some(get_tx_context),
+            ..Default::default()
+        };
+        let context = ExecutionContext::new(&host, ptr::null_mut());
+        let mut cache = TxContextCache::default();
+        assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+        assert_eq!(cache.get(&context).block_number, 42);
+        assert_eq!(cache.get(&context).block_number, 42);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,4 +1,55 @@
+//! An EVM interpreter which can be used as EVMC VM, through the `evmc_create_evmrs` and
+//! `evmc_create_steppable_evmrs` exports, or directly from Rust through [`Evm`].
+//!
+//! ```
+//! use evmrs::{
+//!     evmc_vm::{Address, ExecutionMessage, MessageKind, Revision, StatusCode, Uint256},
+//!     Account, Evm, InMemoryHost,
+//! };
+//!
+//! let code = [
+//!     0x60, 0x02, // PUSH1 2
+//!     0x60, 0x03, // PUSH1 3
+//!     0x01, // ADD
+//!     0x60, 0x00, // PUSH1 0
+//!     0x52, // MSTORE
+//!     0x60, 0x20, // PUSH1 32
+//!     0x60, 0x00, // PUSH1 0
+//!     0xf3, // RETURN
+//! ];
+//! let message = ExecutionMessage::new(
+//!     MessageKind::EVMC_CALL,
+//!     0,
+//!     0,
+//!     100_000,
+//!     Address::default(),
+//!     Address::default(),
+//!     None,
+//!     Uint256::default(),
+//!     Uint256::default(),
+//!     Address::default(),
+//!     None,
+//! );
+//! let mut host = InMemoryHost::new(Revision::EVMC_CANCUN, Default::default());
+//! let result = Evm::new()
+//!     .execute(Revision::EVMC_CANCUN, &code, &message, &mut host)
+//!     .unwrap();
+//! assert_eq!(result.status_code(), StatusCode::EVMC_SUCCESS);
+//! assert_eq!(result.output().unwrap()[31], 5);
+//! ```
+
+mod evm;
 mod evmc;
 mod ffi;
+mod host;
 mod interpreter;
 mod types;
+mod utils;
+
+pub use evm::Evm;
+pub use evmc::EvmRs;
+pub use evmc_vm;
+pub use evmc_vm::{ExecutionResult, StepResult};
+pub use host::{Account, Host, InMemoryHost, Log};
+pub use interpreter::{ExecutionError, FailureReason};
+pub use types::{u256, Opcode};
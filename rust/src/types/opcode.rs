@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use std::mem;
+
 const STOP: u8 = 0x00;
 const ADD: u8 = 0x01;
 const MUL: u8 = 0x02;
@@ -139,17 +141,37 @@ const LOG1: u8 = 0xA1;
 const LOG2: u8 = 0xA2;
 const LOG3: u8 = 0xA3;
 const LOG4: u8 = 0xA4;
+const DATALOAD: u8 = 0xD0;
+const DATALOADN: u8 = 0xD1;
+const DATASIZE: u8 = 0xD2;
+const DATACOPY: u8 = 0xD3;
+const RJUMP: u8 = 0xE0;
+const RJUMPI: u8 = 0xE1;
+const RJUMPV: u8 = 0xE2;
+const CALLF: u8 = 0xE3;
+const RETF: u8 = 0xE4;
+const JUMPF: u8 = 0xE5;
+const DUPN: u8 = 0xE6;
+const SWAPN: u8 = 0xE7;
+const EXCHANGE: u8 = 0xE8;
+const EOFCREATE: u8 = 0xEC;
+const RETURNCONTRACT: u8 = 0xEE;
 const CREATE: u8 = 0xF0;
 const CALL: u8 = 0xF1;
 const CALLCODE: u8 = 0xF2;
 const RETURN: u8 = 0xF3;
 const DELEGATECALL: u8 = 0xF4;
 const CREATE2: u8 = 0xF5;
+const RETURNDATALOAD: u8 = 0xF7;
+const EXTCALL: u8 = 0xF8;
+const EXTDELEGATECALL: u8 = 0xF9;
 const STATICCALL: u8 = 0xFA;
+const EXTSTATICCALL: u8 = 0xFB;
 const REVERT: u8 = 0xFD;
 const INVALID: u8 = 0xFE;
 const SELFDESTRUCT: u8 = 0xFF;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Opcode {
     Stop = STOP,
@@ -291,18 +313,58 @@ pub enum Opcode {
     Log2 = LOG2,
     Log3 = LOG3,
     Log4 = LOG4,
+    DataLoad = DATALOAD,
+    DataLoadN = DATALOADN,
+    DataSize = DATASIZE,
+    DataCopy = DATACOPY,
+    RJump = RJUMP,
+    RJumpI = RJUMPI,
+    RJumpV = RJUMPV,
+    CallF = CALLF,
+    RetF = RETF,
+    JumpF = JUMPF,
+    DupN = DUPN,
+    SwapN = SWAPN,
+    Exchange = EXCHANGE,
+    EofCreate = EOFCREATE,
+    ReturnContract = RETURNCONTRACT,
     Create = CREATE,
     Call = CALL,
     CallCode = CALLCODE,
     Return = RETURN,
     DelegateCall = DELEGATECALL,
     Create2 = CREATE2,
+    ReturnDataLoad = RETURNDATALOAD,
+    ExtCall = EXTCALL,
+    ExtDelegateCall = EXTDELEGATECALL,
     StaticCall = STATICCALL,
+    ExtStaticCall = EXTSTATICCALL,
     Revert = REVERT,
     Invalid = INVALID,
     SelfDestruct = SELFDESTRUCT,
 }
 
+impl TryFrom<u8> for Opcode {
+    type Error = ();
+
+    /// Fails for bytes which are not an opcode in any revision, neither in legacy code nor in EOF
+    /// code.
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        if code_byte_type(byte, false).0 == CodeByteType::DataOrInvalid
+            && code_byte_type(byte, true).0 == CodeByteType::DataOrInvalid
+        {
+            return Err(());
+        }
+        Ok(unsafe {
+            // SAFETY:
+            // [Opcode] has repr(u8) and therefore the same memory layout as u8.
+            // code_byte_type determined that this byte is a valid opcode.
+            // Therefore the value is a valid enum variant.
+            mem::transmute::<u8, Opcode>(byte)
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CodeByteType {
     JumpDest,
@@ -310,7 +372,16 @@ pub enum CodeByteType {
     DataOrInvalid,
 }
 
-pub fn code_byte_type(code_byte: u8) -> (CodeByteType, usize) {
+/// Returns the type of `code_byte` if it is at the position of an opcode, together with the length
+/// of the opcode including its immediates. `eof` selects between legacy code and the code sections
+/// of EOF containers (EIP-3670), which forbid some legacy opcodes and add opcodes with immediates.
+///
+/// The length returned for `RJUMPV` only includes the count of its jump table, which has to be read
+/// to determine the length of the table.
+pub fn code_byte_type(code_byte: u8, eof: bool) -> (CodeByteType, usize) {
+    if eof {
+        return eof_code_byte_type(code_byte);
+    }
     match code_byte {
         STOP | ADD | MUL | SUB | DIV | SDIV | MOD | SMOD | ADDMOD | MULMOD | EXP | SIGNEXTEND
         | LT | GT | SLT | SGT | EQ | ISZERO | AND | OR | XOR | NOT | BYTE | SHL | SHR | SAR
@@ -361,3 +432,20 @@ pub fn code_byte_type(code_byte: u8) -> (CodeByteType, usize) {
         _ => (CodeByteType::DataOrInvalid, 1),
     }
 }
+
+fn eof_code_byte_type(code_byte: u8) -> (CodeByteType, usize) {
+    match code_byte {
+        // Opcodes which observe the code or the gas or which are replaced by EOF opcodes.
+        JUMP | JUMPI | PC | GAS | CODESIZE | CODECOPY | EXTCODESIZE | EXTCODECOPY | EXTCODEHASH
+        | CALL | CALLCODE | DELEGATECALL | STATICCALL | CREATE | CREATE2 | SELFDESTRUCT => {
+            (CodeByteType::DataOrInvalid, 1)
+        }
+        // JUMPDEST is a no-op without JUMP and JUMPI.
+        JUMPDEST => (CodeByteType::Opcode, 1),
+        DATALOAD | DATASIZE | DATACOPY | RETF | RETURNDATALOAD | EXTCALL | EXTDELEGATECALL
+        | EXTSTATICCALL => (CodeByteType::Opcode, 1),
+        DUPN | SWAPN | EXCHANGE | EOFCREATE | RETURNCONTRACT | RJUMPV => (CodeByteType::Opcode, 2),
+        DATALOADN | RJUMP | RJUMPI | CALLF | JUMPF => (CodeByteType::Opcode, 3),
+        _ => code_byte_type(code_byte, false),
+    }
+}
@@ -1,17 +1,13 @@
 use std::{
     cmp::Ordering,
     fmt::{Debug, Display},
-    mem,
+    hash::{Hash, Hasher},
     ops::{
         Add, AddAssign, BitAnd, BitOr, BitXor, Deref, DerefMut, Div, DivAssign, Mul, MulAssign,
         Not, Rem, RemAssign, Shl, Shr, Sub, SubAssign,
     },
 };
 
-use bnum::{
-    types::{I256, U256, U512},
-    BInt, BUint,
-};
 use evmc_vm::{Address, Uint256};
 
 #[allow(non_camel_case_types)]
@@ -70,66 +66,6 @@ impl From<u256> for Uint256 {
     }
 }
 
-impl From<U256> for u256 {
-    fn from(value: U256) -> Self {
-        // TODO bnum has to_be_bytes with feature nightly
-        let be_value = value.to_be();
-        let bytes: [u8; 32] = unsafe {
-            // U256 = BUint<4>
-            // BUint<4> is transparent wrapper around [u64; 4]
-            mem::transmute(be_value)
-        };
-        bytes.into()
-    }
-}
-
-impl From<u256> for U256 {
-    fn from(value: u256) -> Self {
-        BUint::from_be_slice(value.deref()).unwrap()
-    }
-}
-
-impl From<I256> for u256 {
-    fn from(value: I256) -> Self {
-        // TODO bnum has to_be_bytes with feature nightly
-        let be_value = value.to_be();
-        let bytes: [u8; 32] = unsafe {
-            // I256 = is transparent wrapper around U256
-            // U256 = BInt<4>
-            // BUint<4> is transparent wrapper around [u64; 4]
-            mem::transmute(be_value)
-        };
-        bytes.into()
-    }
-}
-
-impl From<u256> for I256 {
-    fn from(value: u256) -> Self {
-        BInt::from_be_slice(value.deref()).unwrap()
-    }
-}
-
-impl From<U512> for u256 {
-    fn from(value: U512) -> Self {
-        // TODO bnum has to_be_bytes with feature nightly
-        let be_value = value.to_be();
-        let bytes64: [u8; 64] = unsafe {
-            // U512 = BUint<8>
-            // BUint<8> is transparent wrapper around [u64; 8]
-            mem::transmute(be_value)
-        };
-        let mut bytes32 = [0; 32];
-        bytes32.copy_from_slice(&bytes64[32..]);
-        bytes32.into()
-    }
-}
-
-impl From<u256> for U512 {
-    fn from(value: u256) -> Self {
-        U512::from_be_slice(&value.0.bytes).unwrap()
-    }
-}
-
 impl From<[u8; 32]> for u256 {
     fn from(value: [u8; 32]) -> Self {
         Self(Uint256 { bytes: value })
@@ -211,14 +147,202 @@ impl u256 {
     }
 }
 
+// The arithmetic is implemented on little endian 64 bit limbs, so limbs[0] holds the least
+// significant bits.
+impl u256 {
+    #[inline(always)]
+    fn into_limbs(self) -> [u64; 4] {
+        let bytes = &self.0.bytes;
+        let mut limbs = [0; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let start = 24 - i * 8;
+            *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
+        }
+        limbs
+    }
+
+    #[inline(always)]
+    fn from_limbs(limbs: [u64; 4]) -> Self {
+        let mut bytes = [0; 32];
+        for (i, limb) in limbs.iter().enumerate() {
+            let start = 24 - i * 8;
+            bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes.into()
+    }
+
+    /// Returns the value as u64 if it fits into 64 bits.
+    #[inline(always)]
+    fn as_u64(&self) -> Option<u64> {
+        let (value, overflow) = self.into_u64_with_overflow();
+        (!overflow).then_some(value)
+    }
+
+    fn is_negative(&self) -> bool {
+        self[0] & 0x80 > 0
+    }
+
+    fn wrapping_neg(self) -> Self {
+        !self + 1u8.into()
+    }
+
+    /// Returns the absolute value of the two's complement interpretation and whether the value
+    /// was negative.
+    fn abs(self) -> (Self, bool) {
+        if self.is_negative() {
+            (self.wrapping_neg(), true)
+        } else {
+            (self, false)
+        }
+    }
+}
+
+/// Adds rhs to lhs and returns the carry.
+#[inline(always)]
+fn add_limbs<const N: usize>(lhs: &mut [u64; N], rhs: &[u64; N]) -> bool {
+    let mut carry = false;
+    for (l, r) in lhs.iter_mut().zip(rhs) {
+        let (sum, carry1) = l.overflowing_add(*r);
+        let (sum, carry2) = sum.overflowing_add(carry as u64);
+        *l = sum;
+        carry = carry1 | carry2;
+    }
+    carry
+}
+
+/// Subtracts rhs from lhs and returns the borrow.
+#[inline(always)]
+fn sub_limbs<const N: usize>(lhs: &mut [u64; N], rhs: &[u64; N]) -> bool {
+    let mut borrow = false;
+    for (l, r) in lhs.iter_mut().zip(rhs) {
+        let (diff, borrow1) = l.overflowing_sub(*r);
+        let (diff, borrow2) = diff.overflowing_sub(borrow as u64);
+        *l = diff;
+        borrow = borrow1 | borrow2;
+    }
+    borrow
+}
+
+/// Computes the full 512 bit product.
+fn mul_limbs_wide(lhs: &[u64; 4], rhs: &[u64; 4]) -> [u64; 8] {
+    let mut res = [0; 8];
+    for (i, l) in lhs.iter().enumerate() {
+        let mut carry = 0;
+        for (j, r) in rhs.iter().enumerate() {
+            let t = res[i + j] as u128 + *l as u128 * *r as u128 + carry;
+            res[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        res[i + 4] = carry as u64;
+    }
+    res
+}
+
+/// Divides the N limb numerator by the non zero divisor and returns quotient and remainder.
+/// This is algorithm D from Knuth, The Art of Computer Programming, Vol. 2, 4.3.1.
+fn div_rem_limbs<const N: usize>(num: &[u64; N], div: &[u64; 4]) -> ([u64; N], [u64; 4]) {
+    let mut quot = [0; N];
+    let mut rem = [0; 4];
+
+    let n = div.iter().rposition(|limb| *limb != 0).unwrap() + 1;
+    let Some(m) = num.iter().rposition(|limb| *limb != 0).map(|m| m + 1) else {
+        return (quot, rem);
+    };
+    if m < n {
+        rem[..m].copy_from_slice(&num[..m]);
+        return (quot, rem);
+    }
+
+    if n == 1 {
+        let div = div[0] as u128;
+        let mut r = 0;
+        for i in (0..m).rev() {
+            let t = (r << 64) | num[i] as u128;
+            quot[i] = (t / div) as u64;
+            r = t % div;
+        }
+        rem[0] = r as u64;
+        return (quot, rem);
+    }
+
+    // Normalize so that the most significant limb of the divisor has its highest bit set. The
+    // normalized numerator needs one additional limb.
+    let shift = div[n - 1].leading_zeros();
+    let shl = |hi: u64, lo: u64| {
+        if shift == 0 {
+            hi
+        } else {
+            (hi << shift) | (lo >> (64 - shift))
+        }
+    };
+    let mut vn = [0; 4];
+    for i in (1..n).rev() {
+        vn[i] = shl(div[i], div[i - 1]);
+    }
+    vn[0] = div[0] << shift;
+    let mut un = [0; 9];
+    un[m] = shl(0, num[m - 1]);
+    for i in (1..m).rev() {
+        un[i] = shl(num[i], num[i - 1]);
+    }
+    un[0] = num[0] << shift;
+
+    const BASE: u128 = 1 << 64;
+    for j in (0..=m - n).rev() {
+        let top = ((un[j + n] as u128) << 64) | un[j + n - 1] as u128;
+        let mut qhat = top / vn[n - 1] as u128;
+        let mut rhat = top % vn[n - 1] as u128;
+        while qhat >= BASE || qhat * vn[n - 2] as u128 > ((rhat << 64) | un[j + n - 2] as u128) {
+            qhat -= 1;
+            rhat += vn[n - 1] as u128;
+            if rhat >= BASE {
+                break;
+            }
+        }
+
+        // Multiply and subtract.
+        let mut borrow: i128 = 0;
+        for i in 0..n {
+            let p = qhat * vn[i] as u128;
+            let t = un[i + j] as i128 - borrow - (p as u64) as i128;
+            un[i + j] = t as u64;
+            borrow = (p >> 64) as i128 - (t >> 64);
+        }
+        let t = un[j + n] as i128 - borrow;
+        un[j + n] = t as u64;
+
+        if t < 0 {
+            // qhat was one too large, so add the divisor back.
+            qhat -= 1;
+            let mut carry = 0;
+            for i in 0..n {
+                let t = un[i + j] as u128 + vn[i] as u128 + carry;
+                un[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            un[j + n] = un[j + n].wrapping_add(carry as u64);
+        }
+        quot[j] = qhat as u64;
+    }
+
+    // Denormalize the remainder.
+    for i in 0..n {
+        rem[i] = if shift == 0 {
+            un[i]
+        } else {
+            (un[i] >> shift) | (un[i + 1] << (64 - shift))
+        };
+    }
+    (quot, rem)
+}
+
 impl Add for u256 {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        let lhs: U256 = self.into();
-        let rhs: U256 = rhs.into();
-
-        lhs.wrapping_add(rhs).into()
+        let mut lhs = self.into_limbs();
+        add_limbs(&mut lhs, &rhs.into_limbs());
+        Self::from_limbs(lhs)
     }
 }
 
@@ -232,10 +356,9 @@ impl Sub for u256 {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        let lhs: U256 = self.into();
-        let rhs: U256 = rhs.into();
-
-        lhs.wrapping_sub(rhs).into()
+        let mut lhs = self.into_limbs();
+        sub_limbs(&mut lhs, &rhs.into_limbs());
+        Self::from_limbs(lhs)
     }
 }
 
@@ -249,10 +372,21 @@ impl Mul for u256 {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        let lhs: U256 = self.into();
-        let rhs: U256 = rhs.into();
-
-        lhs.wrapping_mul(rhs).into()
+        if let (Some(lhs), Some(rhs)) = (self.as_u64(), rhs.as_u64()) {
+            return (lhs as u128 * rhs as u128).into();
+        }
+        let lhs = self.into_limbs();
+        let rhs = rhs.into_limbs();
+        let mut res = [0; 4];
+        for (i, l) in lhs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, r) in rhs.iter().enumerate().take(4 - i) {
+                let t = res[i + j] as u128 + *l as u128 * *r as u128 + carry;
+                res[i + j] = t as u64;
+                carry = t >> 64;
+            }
+        }
+        Self::from_limbs(res)
     }
 }
 
@@ -262,6 +396,17 @@ impl MulAssign for u256 {
     }
 }
 
+impl u256 {
+    /// Returns quotient and remainder. The divisor must not be zero.
+    fn div_rem(self, rhs: Self) -> (Self, Self) {
+        if let (Some(lhs), Some(rhs)) = (self.as_u64(), rhs.as_u64()) {
+            return ((lhs / rhs).into(), (lhs % rhs).into());
+        }
+        let (quot, rem) = div_rem_limbs(&self.into_limbs(), &rhs.into_limbs());
+        (Self::from_limbs(quot), Self::from_limbs(rem))
+    }
+}
+
 impl Div for u256 {
     type Output = Self;
 
@@ -269,10 +414,7 @@ impl Div for u256 {
         if rhs == u256::ZERO {
             return u256::ZERO;
         }
-        let lhs: U256 = self.into();
-        let rhs: U256 = rhs.into();
-
-        lhs.wrapping_div(rhs).into()
+        self.div_rem(rhs).0
     }
 }
 
@@ -287,10 +429,14 @@ impl u256 {
         if rhs == u256::ZERO {
             return u256::ZERO;
         }
-        let lhs: I256 = self.into();
-        let rhs: I256 = rhs.into();
-
-        lhs.wrapping_div(rhs).into()
+        let (lhs, lhs_negative) = self.abs();
+        let (rhs, rhs_negative) = rhs.abs();
+        let quot = lhs.div_rem(rhs).0;
+        if lhs_negative != rhs_negative {
+            quot.wrapping_neg()
+        } else {
+            quot
+        }
     }
 }
 
@@ -301,10 +447,7 @@ impl Rem for u256 {
         if rhs == u256::ZERO {
             return u256::ZERO;
         }
-        let lhs: U256 = self.into();
-        let rhs: U256 = rhs.into();
-
-        lhs.wrapping_rem(rhs).into()
+        self.div_rem(rhs).1
     }
 }
 
@@ -319,47 +462,63 @@ impl u256 {
         if rhs == u256::ZERO {
             return u256::ZERO;
         }
-        let lhs: I256 = self.into();
-        let rhs: I256 = rhs.into();
-
-        lhs.wrapping_rem(rhs).into()
+        let (lhs, lhs_negative) = self.abs();
+        let (rhs, _) = rhs.abs();
+        let rem = lhs.div_rem(rhs).1;
+        if lhs_negative {
+            rem.wrapping_neg()
+        } else {
+            rem
+        }
     }
 
     pub fn addmod(s1: Self, s2: Self, m: Self) -> Self {
         if m == u256::ZERO {
             return u256::ZERO;
         }
-        let s1: U512 = s1.into();
-        let s2: U512 = s2.into();
-        let m: U512 = m.into();
-
-        (s1 + s2).rem(m).into()
+        if let (Some(s1), Some(s2), Some(m)) = (s1.as_u64(), s2.as_u64(), m.as_u64()) {
+            return ((s1 as u128 + s2 as u128) % m as u128).into();
+        }
+        let mut sum = [0; 5];
+        sum[..4].copy_from_slice(&s1.into_limbs());
+        let mut s2_limbs = [0; 5];
+        s2_limbs[..4].copy_from_slice(&s2.into_limbs());
+        add_limbs(&mut sum, &s2_limbs);
+        Self::from_limbs(div_rem_limbs(&sum, &m.into_limbs()).1)
     }
 
     pub fn mulmod(s1: Self, s2: Self, m: Self) -> Self {
         if m == u256::ZERO {
             return u256::ZERO;
         }
-        let f1: U512 = s1.into();
-        let f2: U512 = s2.into();
-        let m: U512 = m.into();
-
-        (f1 * f2).rem(m).into()
+        if let (Some(s1), Some(s2), Some(m)) = (s1.as_u64(), s2.as_u64(), m.as_u64()) {
+            return ((s1 as u128 * s2 as u128) % m as u128).into();
+        }
+        let product = mul_limbs_wide(&s1.into_limbs(), &s2.into_limbs());
+        Self::from_limbs(div_rem_limbs(&product, &m.into_limbs()).1)
     }
 
     pub fn pow(self, exp: Self) -> Self {
-        let base: U256 = self.into();
-        let exp: U256 = exp.into();
-        let mut res = U256::ONE;
-
-        for bit in (0..U256::BITS).rev().map(|bit| exp.bit(bit)) {
-            res = res.wrapping_mul(res);
-            if bit {
-                res = res.wrapping_mul(base);
+        let mut res = u256::from(1u8);
+        let exp = exp.into_limbs();
+        let Some(top) = exp.iter().rposition(|limb| *limb != 0) else {
+            return res;
+        };
+        // Square and multiply, starting with the highest set bit of the exponent.
+        for (i, limb) in exp[..=top].iter().enumerate().rev() {
+            let bits = if i == top {
+                64 - limb.leading_zeros()
+            } else {
+                64
+            };
+            for bit in (0..bits).rev() {
+                res = res * res;
+                if limb & (1 << bit) != 0 {
+                    res *= self;
+                }
             }
         }
-
-        res.into()
+        res
     }
 
     pub fn signextend(self, rhs: Self) -> Self {
@@ -372,15 +531,15 @@ impl u256 {
         let byte = 31 - lhs; // lhs <= 31 so this does not underflow
         let negative = (rhs[byte] & 0x80) > 0;
 
-        let rhs: U256 = rhs.into();
-
-        let res = if negative {
-            rhs | (U256::MAX << ((32 - byte) * 8))
-        } else {
-            rhs & (U256::MAX >> (byte * 8))
-        };
+        let mut res = rhs;
+        res[..byte].fill(if negative { 0xff } else { 0 });
+        res
+    }
+}
 
-        res.into()
+impl From<u128> for u256 {
+    fn from(value: u128) -> Self {
+        Self::from_limbs([value as u64, (value >> 64) as u64, 0, 0])
     }
 }
 
@@ -392,6 +551,12 @@ impl PartialEq for u256 {
 
 impl Eq for u256 {}
 
+impl Hash for u256 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
 impl PartialOrd for u256 {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -400,23 +565,28 @@ impl PartialOrd for u256 {
 
 impl Ord for u256 {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let lhs: U256 = (*self).into();
-        let rhs: U256 = (*other).into();
-        lhs.cmp(&rhs)
+        // The bytes are big endian, so the lexicographical order is the numerical order.
+        (**self).cmp(&**other)
     }
 }
 
 impl u256 {
+    fn scmp(&self, rhs: &Self) -> Ordering {
+        match (self.is_negative(), rhs.is_negative()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            // In two's complement, numbers with the same sign have the same order as unsigned
+            // numbers.
+            _ => self.cmp(rhs),
+        }
+    }
+
     pub fn slt(&self, rhs: &Self) -> bool {
-        let lhs: I256 = (*self).into();
-        let rhs: I256 = (*rhs).into();
-        lhs.cmp(&rhs) == Ordering::Less
+        self.scmp(rhs) == Ordering::Less
     }
 
     pub fn sgt(&self, rhs: &Self) -> bool {
-        let lhs: I256 = (*self).into();
-        let rhs: I256 = (*rhs).into();
-        lhs.cmp(&rhs) == Ordering::Greater
+        self.scmp(rhs) == Ordering::Greater
     }
 }
 
@@ -467,11 +637,43 @@ impl Not for u256 {
 
 impl u256 {
     pub fn byte(&self, index: Self) -> Self {
-        if index >= 32u8.into() {
-            return u256::ZERO;
+        match index.as_u64() {
+            Some(idx) if idx < 32 => self[idx as usize].into(),
+            _ => u256::ZERO,
         }
-        let idx = index[31];
-        self[idx as usize].into()
+    }
+
+    /// Returns the shift amount if it is less than 256.
+    fn shift_amount(self) -> Option<u32> {
+        self.as_u64()
+            .filter(|shift| *shift < 256)
+            .map(|shift| shift as u32)
+    }
+
+    fn shl_limbs(limbs: [u64; 4], shift: u32) -> [u64; 4] {
+        let limb_shift = (shift / 64) as usize;
+        let bit_shift = shift % 64;
+        let mut res = [0; 4];
+        for i in limb_shift..4 {
+            res[i] = limbs[i - limb_shift] << bit_shift;
+            if bit_shift > 0 && i > limb_shift {
+                res[i] |= limbs[i - limb_shift - 1] >> (64 - bit_shift);
+            }
+        }
+        res
+    }
+
+    fn shr_limbs(limbs: [u64; 4], shift: u32) -> [u64; 4] {
+        let limb_shift = (shift / 64) as usize;
+        let bit_shift = shift % 64;
+        let mut res = [0; 4];
+        for i in 0..4 - limb_shift {
+            res[i] = limbs[i + limb_shift] >> bit_shift;
+            if bit_shift > 0 && i + limb_shift < 3 {
+                res[i] |= limbs[i + limb_shift + 1] << (64 - bit_shift);
+            }
+        }
+        res
     }
 }
 
@@ -479,12 +681,10 @@ impl Shl for u256 {
     type Output = Self;
 
     fn shl(self, rhs: Self) -> Self::Output {
-        if rhs > u256::from(255u8) {
+        let Some(shift) = rhs.shift_amount() else {
             return u256::ZERO;
-        }
-        let value: U256 = self.into();
-        let shift = rhs[31] as u32;
-        (value.wrapping_shl(shift)).into()
+        };
+        Self::from_limbs(Self::shl_limbs(self.into_limbs(), shift))
     }
 }
 
@@ -492,31 +692,171 @@ impl Shr for u256 {
     type Output = Self;
 
     fn shr(self, rhs: Self) -> Self::Output {
-        if rhs > u256::from(255u8) {
+        let Some(shift) = rhs.shift_amount() else {
             return u256::ZERO;
-        }
-        let value: U256 = self.into();
-        let shift = rhs[31] as u32;
-        (value.wrapping_shr(shift)).into()
+        };
+        Self::from_limbs(Self::shr_limbs(self.into_limbs(), shift))
     }
 }
 
 impl u256 {
     pub fn sar(self, rhs: Self) -> Self {
-        let negative = self[0] & 0x80 > 0;
-        if rhs > u256::from(255u8) {
-            if negative {
-                return u256::MAX;
+        let negative = self.is_negative();
+        let Some(shift) = rhs.shift_amount() else {
+            return if negative { u256::MAX } else { u256::ZERO };
+        };
+        let shr = Self::from_limbs(Self::shr_limbs(self.into_limbs(), shift));
+        if negative {
+            shr | Self::from_limbs(Self::shl_limbs([u64::MAX; 4], 255 - shift))
+        } else {
+            shr
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bnum::types::{I256, U256, U512};
+    use proptest::prelude::*;
+
+    use crate::types::u256;
+
+    fn to_bnum(value: u256) -> U256 {
+        U256::from_digits(value.into_limbs())
+    }
+
+    fn from_bnum(value: U256) -> u256 {
+        u256::from_limbs(*value.digits())
+    }
+
+    fn to_bnum_signed(value: u256) -> I256 {
+        I256::from_bits(to_bnum(value))
+    }
+
+    fn from_bnum_signed(value: I256) -> u256 {
+        from_bnum(value.to_bits())
+    }
+
+    fn to_bnum_wide(value: u256) -> U512 {
+        let mut digits = [0; 8];
+        digits[..4].copy_from_slice(&value.into_limbs());
+        U512::from_digits(digits)
+    }
+
+    fn from_bnum_wide(value: U512) -> u256 {
+        u256::from_limbs(value.digits()[..4].try_into().unwrap())
+    }
+
+    /// Generates values with random limbs set to zero, so that the fast paths and all branches of
+    /// the division are covered.
+    fn value() -> impl Strategy<Value = u256> {
+        prop_oneof![
+            1 => Just(u256::ZERO),
+            1 => Just(u256::MAX),
+            1 => Just(u256::from(1u8) << u256::from(255u8)),
+            8 => (any::<[u64; 4]>(), any::<u8>()).prop_map(|(mut limbs, mask)| {
+                for (i, limb) in limbs.iter_mut().enumerate() {
+                    if mask & (1 << i) != 0 {
+                        *limb = 0;
+                    }
+                    if mask & (1 << (i + 4)) != 0 {
+                        *limb >>= 32;
+                    }
+                }
+                u256::from_limbs(limbs)
+            }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn add_sub_mul(a in value(), b in value()) {
+            prop_assert_eq!(a + b, from_bnum(to_bnum(a).wrapping_add(to_bnum(b))));
+            prop_assert_eq!(a - b, from_bnum(to_bnum(a).wrapping_sub(to_bnum(b))));
+            prop_assert_eq!(a * b, from_bnum(to_bnum(a).wrapping_mul(to_bnum(b))));
+        }
+
+        #[test]
+        fn div_rem(a in value(), b in value()) {
+            // Division by zero returns zero.
+            let (div, rem, sdiv, srem) = if b == u256::ZERO {
+                (u256::ZERO, u256::ZERO, u256::ZERO, u256::ZERO)
+            } else {
+                let (a_signed, b_signed) = (to_bnum_signed(a), to_bnum_signed(b));
+                (
+                    from_bnum(to_bnum(a).wrapping_div(to_bnum(b))),
+                    from_bnum(to_bnum(a).wrapping_rem(to_bnum(b))),
+                    from_bnum_signed(a_signed.wrapping_div(b_signed)),
+                    from_bnum_signed(a_signed.wrapping_rem(b_signed)),
+                )
+            };
+            prop_assert_eq!(a / b, div);
+            prop_assert_eq!(a % b, rem);
+            prop_assert_eq!(a.sdiv(b), sdiv);
+            prop_assert_eq!(a.srem(b), srem);
+        }
+
+        #[test]
+        fn addmod_mulmod(a in value(), b in value(), m in value()) {
+            let (addmod, mulmod) = if m == u256::ZERO {
+                (u256::ZERO, u256::ZERO)
             } else {
-                return u256::ZERO;
+                let (a, b, m) = (to_bnum_wide(a), to_bnum_wide(b), to_bnum_wide(m));
+                (from_bnum_wide((a + b) % m), from_bnum_wide((a * b) % m))
+            };
+            prop_assert_eq!(u256::addmod(a, b, m), addmod);
+            prop_assert_eq!(u256::mulmod(a, b, m), mulmod);
+        }
+
+        #[test]
+        fn pow(base in value(), exp in value()) {
+            let (base_bnum, exp_bnum) = (to_bnum(base), to_bnum(exp));
+            let mut res = U256::ONE;
+            for bit in (0..U256::BITS).rev().map(|bit| exp_bnum.bit(bit)) {
+                res = res.wrapping_mul(res);
+                if bit {
+                    res = res.wrapping_mul(base_bnum);
+                }
             }
+            prop_assert_eq!(base.pow(exp), from_bnum(res));
         }
-        let value: U256 = self.into();
-        let shift = rhs[31] as u32;
-        let mut shr = value.wrapping_shr(shift);
-        if negative {
-            shr |= U256::MAX.wrapping_shl(255 - shift);
+
+        #[test]
+        fn shifts(value in value(), shift in 0u16..300) {
+            let shift_u256 = u256::from(shift as u64);
+            let (shl, shr) = if shift > 255 {
+                (U256::ZERO, U256::ZERO)
+            } else {
+                (
+                    to_bnum(value).wrapping_shl(shift as u32),
+                    to_bnum(value).wrapping_shr(shift as u32),
+                )
+            };
+            prop_assert_eq!(value << shift_u256, from_bnum(shl));
+            prop_assert_eq!(value >> shift_u256, from_bnum(shr));
+            let sar = to_bnum_signed(value).wrapping_shr(shift.min(255) as u32);
+            prop_assert_eq!(value.sar(shift_u256), from_bnum_signed(sar));
+        }
+
+        #[test]
+        fn compare_and_byte(a in value(), b in value(), index in 0u8..40) {
+            prop_assert_eq!(a.cmp(&b), to_bnum(a).cmp(&to_bnum(b)));
+            prop_assert_eq!(a.slt(&b), to_bnum_signed(a) < to_bnum_signed(b));
+            prop_assert_eq!(a.sgt(&b), to_bnum_signed(a) > to_bnum_signed(b));
+            let byte = if index < 32 { a[index as usize] } else { 0 };
+            prop_assert_eq!(a.byte(index.into()), byte.into());
+        }
+
+        #[test]
+        fn signextend(value in value(), byte in 0u8..34) {
+            let res = if byte > 31 {
+                value
+            } else {
+                let bits = (byte as u32 + 1) * 8;
+                let signed = to_bnum_signed(value).wrapping_shl(256 - bits).wrapping_shr(256 - bits);
+                from_bnum_signed(signed)
+            };
+            prop_assert_eq!(u256::from(byte).signextend(value), res);
         }
-        shr.into()
     }
 }
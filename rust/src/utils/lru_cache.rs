@@ -0,0 +1,184 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hash},
+    sync::Mutex,
+};
+
+/// A thread-safe cache with a fixed maximum number of entries. When the cache is full, inserting a
+/// new entry evicts the least recently used one. The keys are hashed with `S`.
+#[derive(Debug)]
+pub struct LruCache<K, V, const CAPACITY: usize, S = RandomState> {
+    inner: Mutex<Inner<K, V, S>>,
+}
+
+impl<K, V, const CAPACITY: usize, S> Default for LruCache<K, V, CAPACITY, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const CAPACITY: usize, S> LruCache<K, V, CAPACITY, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Default,
+{
+    pub fn new() -> Self {
+        assert!(CAPACITY > 0);
+        Self {
+            inner: Mutex::new(Inner {
+                entries: Vec::new(),
+                index: HashMap::default(),
+                head: NONE,
+                tail: NONE,
+            }),
+        }
+    }
+
+    /// Returns the value for the given key and marks it as most recently used.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.inner.lock().unwrap();
+        let entry = *inner.index.get(key)?;
+        inner.touch(entry);
+        Some(inner.entries[entry].value.clone())
+    }
+
+    /// Adds or updates the value for the given key and returns it. If the cache is full, the least
+    /// recently used entry is evicted.
+    pub fn insert(&self, key: K, value: V) -> V {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(&entry) = inner.index.get(&key) {
+            inner.entries[entry].value = value.clone();
+            inner.touch(entry);
+            return value;
+        }
+        inner.insert_new::<CAPACITY>(key, value)
+    }
+
+    /// Returns the value for the given key or inserts the value computed by `f`, while holding the
+    /// lock only once. `f` runs while the cache is locked, so it should be cheap.
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> V {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(&entry) = inner.index.get(&key) {
+            inner.touch(entry);
+            return inner.entries[entry].value.clone();
+        }
+        inner.insert_new::<CAPACITY>(key, f())
+    }
+}
+
+const NONE: usize = usize::MAX;
+
+#[derive(Debug)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    pred: usize,
+    succ: usize,
+}
+
+/// Doubly linked list of entries ordered from most (head) to least (tail) recently used. The links
+/// are indices into `entries` so that no unsafe code is required.
+#[derive(Debug)]
+struct Inner<K, V, S> {
+    entries: Vec<Entry<K, V>>,
+    index: HashMap<K, usize, S>,
+    head: usize,
+    tail: usize,
+}
+
+impl<K, V, S> Inner<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    /// Inserts a key which is not in the cache yet.
+    fn insert_new<const CAPACITY: usize>(&mut self, key: K, value: V) -> V {
+        let entry = if self.entries.len() < CAPACITY {
+            self.entries.push(Entry {
+                key: key.clone(),
+                value: value.clone(),
+                pred: NONE,
+                succ: NONE,
+            });
+            self.entries.len() - 1
+        } else {
+            let entry = self.tail;
+            self.unlink(entry);
+            let old_key = self.entries[entry].key.clone();
+            self.index.remove(&old_key);
+            self.entries[entry].key = key.clone();
+            self.entries[entry].value = value.clone();
+            entry
+        };
+        self.push_front(entry);
+        self.index.insert(key, entry);
+
+        value
+    }
+
+    fn touch(&mut self, entry: usize) {
+        if entry != self.head {
+            self.unlink(entry);
+            self.push_front(entry);
+        }
+    }
+
+    fn unlink(&mut self, entry: usize) {
+        let Entry { pred, succ, .. } = self.entries[entry];
+        if pred == NONE {
+            self.head = succ;
+        } else {
+            self.entries[pred].succ = succ;
+        }
+        if succ == NONE {
+            self.tail = pred;
+        } else {
+            self.entries[succ].pred = pred;
+        }
+    }
+
+    fn push_front(&mut self, entry: usize) {
+        self.entries[entry].pred = NONE;
+        self.entries[entry].succ = self.head;
+        if self.head == NONE {
+            self.tail = entry;
+        } else {
+            self.entries[self.head].pred = entry;
+        }
+        self.head = entry;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::LruCache;
+
+    #[test]
+    fn lru_cache_get_returns_inserted_value() {
+        let cache = LruCache::<u8, u8, 2>::new();
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.insert(1, 10), 10);
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.insert(1, 11), 11);
+        assert_eq!(cache.get(&1), Some(11));
+    }
+
+    #[test]
+    fn lru_cache_evicts_least_recently_used() {
+        let cache = LruCache::<u8, u8, 2>::new();
+        cache.insert(1, 10);
+        cache.insert(2, 20);
+        cache.get(&1);
+        cache.insert(3, 30);
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&3), Some(30));
+    }
+}
@@ -0,0 +1,5 @@
+mod lru_cache;
+mod pool;
+
+pub use lru_cache::LruCache;
+pub use pool::Pool;
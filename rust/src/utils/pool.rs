@@ -0,0 +1,71 @@
+use std::cell::RefCell;
+
+/// A pool of buffers which can be reused instead of allocating new ones. It is not thread-safe and
+/// is meant to be used in a `thread_local`.
+#[derive(Debug)]
+pub struct Pool<T> {
+    buffers: RefCell<Vec<Vec<T>>>,
+    max_buffers: usize,
+    max_capacity: usize,
+}
+
+impl<T> Pool<T> {
+    /// Creates a pool which keeps at most `max_buffers` buffers. Buffers with a capacity larger
+    /// than `max_capacity` are not kept.
+    pub const fn new(max_buffers: usize, max_capacity: usize) -> Self {
+        Self {
+            buffers: RefCell::new(Vec::new()),
+            max_buffers,
+            max_capacity,
+        }
+    }
+
+    /// Returns an empty buffer with at least `capacity` elements of capacity.
+    pub fn take(&self, capacity: usize) -> Vec<T> {
+        let mut buffer = self.buffers.borrow_mut().pop().unwrap_or_default();
+        buffer.reserve(capacity);
+        buffer
+    }
+
+    /// Clears the buffer and keeps it for later use.
+    pub fn recycle(&self, mut buffer: Vec<T>) {
+        if buffer.capacity() == 0 || buffer.capacity() > self.max_capacity {
+            return;
+        }
+        let mut buffers = self.buffers.borrow_mut();
+        if buffers.len() < self.max_buffers {
+            buffer.clear();
+            buffers.push(buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::Pool;
+
+    #[test]
+    fn pool_reuses_buffers() {
+        let pool = Pool::<u8>::new(1, 64);
+        let mut buffer = pool.take(32);
+        buffer.push(1);
+        let ptr = buffer.as_ptr();
+        pool.recycle(buffer);
+
+        let buffer = pool.take(32);
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn pool_limits_buffers() {
+        let pool = Pool::<u8>::new(1, 64);
+        pool.recycle(Vec::with_capacity(128));
+        assert_eq!(pool.take(0).capacity(), 0);
+
+        pool.recycle(Vec::with_capacity(32));
+        pool.recycle(Vec::with_capacity(32));
+        assert_eq!(pool.take(0).capacity(), 32);
+        assert_eq!(pool.take(0).capacity(), 0);
+    }
+}